@@ -0,0 +1,85 @@
+use crate::board_representation::{Board, Bitboard, Square};
+use crate::board_representation::move_gen::*;
+use crate::utils::{Color, PieceKind};
+
+// questions about which pieces attack which squares, for either colour and not just the side to move
+impl Board {
+  pub fn get_occupancy(&self) -> Bitboard {
+    self.all_white_pieces() | self.all_black_pieces()
+  }
+  pub fn get_colour_occupancy(&self, color: Color) -> Bitboard {
+    if color.is_white() { self.all_white_pieces() } else { self.all_black_pieces() }
+  }
+
+  // every piece of either colour attacking the square, with sliders seeing through anything that isn't in the occupancy.
+  // pieces that aren't in the occupancy dont count either, so pieces can be taken off one at a time for static exchange evaluation
+  pub fn attackers_to(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+    let queens = self.pieces_of_kind(PieceKind::Queen);
+    let orthogonal_sliders = queens | self.pieces_of_kind(PieceKind::Rook);
+    let diagonal_sliders = queens | self.pieces_of_kind(PieceKind::Bishop);
+    let knights = self.pieces_of_kind(PieceKind::Knight);
+    let kings = self.pieces_of_kind(PieceKind::King);
+
+    // a pawn attacks the square if a pawn of the other colour on the square would attack it
+    let white_pawns = pawn_attack_squares(square.bitboard(), false) & self.pieces(Color::White, PieceKind::Pawn);
+    let black_pawns = pawn_attack_squares(square.bitboard(), true) & self.pieces(Color::Black, PieceKind::Pawn);
+
+    let attackers = (get_rook_moves(square, occupancy) & orthogonal_sliders)
+      | (get_bishop_moves(square, occupancy) & diagonal_sliders)
+      | (knight_moves(square.bitboard()) & knights)
+      | (king_moves(square.bitboard()) & kings)
+      | white_pawns | black_pawns;
+    attackers & occupancy
+  }
+  pub fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+    !(self.attackers_to(square, self.get_occupancy()) & self.get_colour_occupancy(by)).is_empty()
+  }
+
+  pub fn checkers(&self) -> Bitboard { // the pieces giving check to the side to move, none if the position is impossible
+    if !self.is_valid() {
+      return Bitboard::EMPTY;
+    }
+    let king_square = self.king_square(self.white_to_move);
+    self.attackers_to(king_square, self.get_occupancy()) & self.get_colour_occupancy(!self.side_to_move())
+  }
+  pub fn pinned(&self) -> Bitboard { // the side to move's pieces that cant leave the line between their king and an enemy slider
+    self.pinned_pieces
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn squares(names: &[&str]) -> Bitboard {
+    names.iter().map(|name| Square::from_algebraic(name).unwrap()).collect()
+  }
+
+  #[test]
+  fn attackers() {
+    let board = Board::new("4k3/8/3n4/2p5/4q3/3P1N2/8/R3K2R w KQ - 0 1");
+    let occupancy = board.get_occupancy();
+
+    let e4 = Square::from_algebraic("e4").unwrap();
+    assert_eq!(board.attackers_to(e4, occupancy), squares(&["d3", "d6"]));
+    assert!(board.is_square_attacked(e4, Color::White));
+    assert!(board.is_square_attacked(e4, Color::Black));
+
+    // pawns attack diagonally forwards for their own colour
+    let d4 = Square::from_algebraic("d4").unwrap();
+    assert_eq!(board.attackers_to(d4, occupancy), squares(&["c5", "e4", "f3"]));
+    assert!(!board.is_square_attacked(Square::from_algebraic("a3").unwrap(), Color::Black));
+
+    // taking the queen out of the occupancy takes away the check
+    let e1 = Square::from_algebraic("e1").unwrap();
+    assert_eq!(board.checkers(), squares(&["e4"]));
+    assert!((board.attackers_to(e1, occupancy ^ squares(&["e4"])) & board.get_colour_occupancy(Color::Black)).is_empty());
+  }
+
+  #[test]
+  fn pins() {
+    let board = Board::new("4k3/4r3/8/8/1b6/8/3NR3/4K3 w - - 0 1");
+    assert_eq!(board.pinned(), squares(&["d2", "e2"]));
+    assert!(board.checkers().is_empty());
+  }
+}
@@ -0,0 +1,248 @@
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+// squares are numbered from h1 = 0 to a8 = 63, so moving towards the a file is a higher index.
+// nothing outside of this file should have to know that
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Square(u8);
+impl Square {
+  pub const fn new(index: u8) -> Self {
+    assert!(index < 64, "square index out of range");
+    Self(index)
+  }
+  pub const fn from_file_rank(file: u8, rank: u8) -> Self { // both count from 0, so a1 is (0, 0)
+    Self::new(rank * 8 + (7 - file))
+  }
+  pub fn from_algebraic(name: &str) -> Option<Self> {
+    let mut chars = name.chars();
+    let (Some(file), Some(rank), None) = (chars.next(), chars.next(), chars.next()) else {
+      return None;
+    };
+    if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+      return None;
+    }
+    Some(Self::from_file_rank(file as u8 - b'a', rank as u8 - b'1'))
+  }
+  pub fn all() -> impl Iterator<Item = Square> {
+    (0..64).map(Square)
+  }
+
+  pub const fn index(self) -> usize {
+    self.0 as usize
+  }
+  pub const fn file(self) -> u8 { // a file is 0
+    7 - self.0 % 8
+  }
+  pub const fn rank(self) -> u8 { // first rank is 0
+    self.0 / 8
+  }
+  pub const fn bitboard(self) -> Bitboard {
+    Bitboard(1 << self.0)
+  }
+  pub fn offset(self, direction: Direction) -> Option<Square> { // the square next to this one, if it's on the board
+    self.bitboard().shift(direction).first_square()
+  }
+}
+impl fmt::Display for Square {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+  }
+}
+
+// from white's point of view, so north is towards the 8th rank and east is towards the h file
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+  North,
+  South,
+  East,
+  West,
+  NorthEast,
+  NorthWest,
+  SouthEast,
+  SouthWest
+}
+impl Direction {
+  pub const ALL: [Direction; 8] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+    Direction::NorthEast,
+    Direction::NorthWest,
+    Direction::SouthEast,
+    Direction::SouthWest
+  ];
+  pub fn forward(is_white: bool) -> Self { // the way pawns of that colour move
+    if is_white { Direction::North } else { Direction::South }
+  }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Bitboard(pub u64);
+impl Bitboard {
+  pub const EMPTY: Bitboard = Bitboard(0);
+  pub const ALL: Bitboard = Bitboard(u64::MAX);
+  pub const FILE_A: Bitboard = Bitboard(0x8080808080808080);
+  pub const FILE_H: Bitboard = Bitboard(0x0101010101010101);
+  pub const RANK_1: Bitboard = Bitboard(0x00000000000000FF);
+  pub const RANK_8: Bitboard = Bitboard(0xFF00000000000000);
+  pub const LIGHT_SQUARES: Bitboard = Bitboard(0xAA55AA55AA55AA55);
+
+  pub const fn file(file: u8) -> Self {
+    Bitboard(Self::FILE_A.0 >> file)
+  }
+  pub const fn rank(rank: u8) -> Self {
+    Bitboard(Self::RANK_1.0 << (rank * 8))
+  }
+
+  pub const fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+  pub const fn contains(self, square: Square) -> bool {
+    self.0 & (1 << square.0) != 0
+  }
+  pub const fn count(self) -> u32 {
+    self.0.count_ones()
+  }
+  pub fn first_square(self) -> Option<Square> { // the lowest index, which is the square nearest h1
+    if self.is_empty() { None } else { Some(Square(self.0.trailing_zeros() as u8)) }
+  }
+  pub fn last_square(self) -> Option<Square> { // the highest index, nearest a8
+    if self.is_empty() { None } else { Some(Square(63 - self.0.leading_zeros() as u8)) }
+  }
+  pub fn pop_first_square(&mut self) -> Option<Square> {
+    let square = self.first_square()?;
+    self.0 &= self.0 - 1;
+    Some(square)
+  }
+
+  pub const fn shift(self, direction: Direction) -> Self { // anything that would wrap around onto the other side of the board falls off instead
+    let not_a_file = self.0 & !Self::FILE_A.0;
+    let not_h_file = self.0 & !Self::FILE_H.0;
+    Bitboard(match direction {
+      Direction::North => self.0 << 8,
+      Direction::South => self.0 >> 8,
+      Direction::East => not_h_file >> 1,
+      Direction::West => not_a_file << 1,
+      Direction::NorthEast => not_h_file << 7,
+      Direction::NorthWest => not_a_file << 9,
+      Direction::SouthEast => not_h_file >> 9,
+      Direction::SouthWest => not_a_file >> 7,
+    })
+  }
+}
+
+// goes through the squares from h1 to a8, popping each one off a copy of the bitboard so nothing gets allocated
+pub struct BitboardIter(Bitboard);
+impl Iterator for BitboardIter {
+  type Item = Square;
+
+  fn next(&mut self) -> Option<Square> {
+    self.0.pop_first_square()
+  }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let count = self.0.count() as usize;
+    (count, Some(count))
+  }
+}
+impl ExactSizeIterator for BitboardIter {}
+impl IntoIterator for Bitboard {
+  type Item = Square;
+  type IntoIter = BitboardIter;
+
+  fn into_iter(self) -> BitboardIter {
+    BitboardIter(self)
+  }
+}
+impl FromIterator<Square> for Bitboard {
+  fn from_iter<I: IntoIterator<Item = Square>>(squares: I) -> Self {
+    squares.into_iter().fold(Bitboard::EMPTY, |bitboard, square| bitboard | square.bitboard())
+  }
+}
+impl From<Square> for Bitboard {
+  fn from(square: Square) -> Self {
+    square.bitboard()
+  }
+}
+
+impl BitAnd for Bitboard {
+  type Output = Self;
+  fn bitand(self, other: Self) -> Self {
+    Bitboard(self.0 & other.0)
+  }
+}
+impl BitOr for Bitboard {
+  type Output = Self;
+  fn bitor(self, other: Self) -> Self {
+    Bitboard(self.0 | other.0)
+  }
+}
+impl BitXor for Bitboard {
+  type Output = Self;
+  fn bitxor(self, other: Self) -> Self {
+    Bitboard(self.0 ^ other.0)
+  }
+}
+impl Not for Bitboard {
+  type Output = Self;
+  fn not(self) -> Self {
+    Bitboard(!self.0)
+  }
+}
+impl BitAndAssign for Bitboard {
+  fn bitand_assign(&mut self, other: Self) {
+    self.0 &= other.0;
+  }
+}
+impl BitOrAssign for Bitboard {
+  fn bitor_assign(&mut self, other: Self) {
+    self.0 |= other.0;
+  }
+}
+impl BitXorAssign for Bitboard {
+  fn bitxor_assign(&mut self, other: Self) {
+    self.0 ^= other.0;
+  }
+}
+
+// the board as white sees it, 1 for every square in the bitboard. handy when something goes wrong
+impl fmt::Display for Bitboard {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for rank in (0..8).rev() {
+      let row: Vec<&str> = (0..8).map(|file| if self.contains(Square::from_file_rank(file, rank)) { "1" } else { "." }).collect();
+      writeln!(f, "{}", row.join(" "))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn squares_and_shifts() {
+    let e4 = Square::from_algebraic("e4").unwrap();
+    assert_eq!((e4.file(), e4.rank()), (4, 3));
+    assert_eq!(e4.to_string(), "e4");
+    assert_eq!(Square::new(0).to_string(), "h1");
+    assert_eq!(Square::new(63).to_string(), "a8");
+    assert!(Square::from_algebraic("i9").is_none());
+    assert!(Square::all().all(|square| Square::from_algebraic(&square.to_string()) == Some(square)));
+
+    // nothing wraps onto the other side of the board
+    assert!(Bitboard::FILE_H.shift(Direction::East).is_empty());
+    assert!(Bitboard::FILE_A.shift(Direction::NorthWest).is_empty());
+    assert!(Bitboard::RANK_8.shift(Direction::North).is_empty());
+    assert_eq!(e4.offset(Direction::NorthEast), Square::from_algebraic("f5"));
+    assert_eq!(Square::from_algebraic("h4").unwrap().offset(Direction::East), None);
+  }
+
+  #[test]
+  fn iterates_from_h1_to_a8() {
+    let squares: Vec<String> = (Bitboard::RANK_1 & (Bitboard::FILE_A | Bitboard::file(4))).into_iter().map(|square| square.to_string()).collect();
+    assert_eq!(squares, ["e1", "a1"]);
+    assert_eq!(Bitboard::rank(3).into_iter().len(), 8);
+    assert_eq!(Bitboard::ALL.into_iter().collect::<Bitboard>(), Bitboard::ALL);
+  }
+}
@@ -0,0 +1,78 @@
+use crate::board_representation::Board;
+
+// the knight placements for each of the 10 ways to put two knights on the five squares left after the bishops and queen
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+// the starting position for a chess960 position number (0 to 959), numbered the standard (Scharnagl) way so 518 is the normal starting position
+pub fn chess960_fen(position_number: u32) -> String {
+  assert!(position_number < 960, "there are only 960 chess960 positions, got {}", position_number);
+  let mut back_rank = [' '; 8]; // a file first
+  let mut n = position_number as usize;
+
+  back_rank[n % 4 * 2 + 1] = 'b'; // light squared bishop on b, d, f or h
+  n /= 4;
+  back_rank[n % 4 * 2] = 'b'; // dark squared bishop on a, c, e or g
+  n /= 4;
+
+  let place_on_empty = |back_rank: &mut [char; 8], empty_index: usize, piece: char| {
+    let file = (0..8).filter(|file| back_rank[*file] == ' ').nth(empty_index).unwrap();
+    back_rank[file] = piece;
+  };
+  place_on_empty(&mut back_rank, n % 6, 'q');
+  n /= 6;
+
+  let (first_knight, second_knight) = KNIGHT_PLACEMENTS[n];
+  place_on_empty(&mut back_rank, second_knight, 'n'); // the second one first, so placing it doesnt move where the first one goes
+  place_on_empty(&mut back_rank, first_knight, 'n');
+
+  // what's left is always rook, king, rook
+  for piece in ['r', 'k', 'r'] {
+    place_on_empty(&mut back_rank, 0, piece);
+  }
+
+  let black_pieces: String = back_rank.iter().collect();
+  format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", black_pieces, black_pieces.to_ascii_uppercase())
+}
+
+impl Board {
+  pub fn new_chess960(position_number: u32) -> Self {
+    Self::from_fen_chess960(&chess960_fen(position_number)).unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board_representation::STARTING_FEN;
+
+  #[test]
+  fn start_positions() {
+    assert_eq!(chess960_fen(518), STARTING_FEN);
+    assert_eq!(chess960_fen(0), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert_eq!(chess960_fen(959), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+
+    let mut positions: Vec<String> = (0..960).map(chess960_fen).collect();
+    positions.sort();
+    positions.dedup();
+    assert_eq!(positions.len(), 960);
+  }
+
+  #[test]
+  fn castles_onto_the_rook() {
+    let board = Board::new_chess960(518);
+    assert!(board.is_chess960());
+    assert_eq!(board.to_fen(), STARTING_FEN);
+
+    assert!(Board::from_fen("1rk5/8/8/8/8/8/8/1RK5 w KQkq - 0 1").is_err()); // no rooks on the kingside
+
+    // king on c1 and rook on b1, castling queenside swaps them round
+    let mut board = Board::from_fen("1rk4r/8/8/8/8/8/8/1RK4R w BHbh - 0 1").unwrap();
+    assert!(board.is_chess960());
+    let castle = board.parse_san("O-O-O").unwrap();
+    assert_eq!(castle.to_uci(), "c1b1");
+    board.make_move(castle);
+    assert_eq!(board.to_fen(), "1rk4r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+    board.unmake_move();
+    assert_eq!(board.to_fen(), "1rk4r/8/8/8/8/8/8/1RK4R w KQkq - 0 1");
+  }
+}
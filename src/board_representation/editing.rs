@@ -0,0 +1,144 @@
+use crate::board_representation::{Board, Bitboard, Square, CastlingRights, PositionError};
+use crate::board_representation::zobrist::{side_key, en_passent_key};
+use crate::utils::{Color, Piece};
+
+// for setting up positions without going through a FEN. every edit is made even if it leaves the position impossible,
+// so a position can be built up a piece at a time. until an edit comes back Ok the board has no moves and can't be played on or searched
+impl Board {
+  pub fn put_piece(&mut self, square: Square, piece: Piece) -> Result<(), PositionError> { // replaces whatever was on the square
+    if let Some(old_piece) = self.piece_at(square) {
+      self.toggle_pieces(old_piece, square.bitboard());
+    }
+    self.toggle_pieces(piece, square.bitboard());
+    self.finish_edit()
+  }
+  pub fn remove_piece(&mut self, square: Square) -> Result<(), PositionError> {
+    if let Some(old_piece) = self.piece_at(square) {
+      self.toggle_pieces(old_piece, square.bitboard());
+    }
+    self.finish_edit()
+  }
+  pub fn set_side_to_move(&mut self, color: Color) -> Result<(), PositionError> {
+    if self.side_to_move() != color {
+      self.white_to_move = color.is_white();
+      self.hash ^= side_key();
+    }
+    self.finish_edit()
+  }
+  // the king and rook have to already be on the squares they castle from, which are the ones the board was set up with
+  pub fn set_castling_rights(&mut self, color: Color, is_kingside: bool, can_castle: bool) -> Result<(), PositionError> {
+    self.hash ^= self.castling_key();
+    let flags = self.castling_flags_mut(color);
+    if can_castle {
+      if flags.king_moved { // giving the king its right back would give back the other side's too
+        flags.rook_kingside_moved = true;
+        flags.rook_queenside_moved = true;
+        flags.king_moved = false;
+      }
+      if is_kingside { flags.rook_kingside_moved = false; } else { flags.rook_queenside_moved = false; }
+    }
+    else if is_kingside {
+      flags.rook_kingside_moved = true;
+    }
+    else {
+      flags.rook_queenside_moved = true;
+    }
+    self.hash ^= self.castling_key();
+    self.finish_edit()
+  }
+  pub fn set_en_passant(&mut self, square: Option<Square>) -> Result<(), PositionError> { // the square the pawn skipped over, not the pawn
+    self.hash ^= en_passent_key(self.en_passent_square) ^ en_passent_key(square);
+    self.en_passent_square = square;
+    self.finish_edit()
+  }
+
+  fn finish_edit(&mut self) -> Result<(), PositionError> {
+    self.undo_stack.clear(); // the moves before an edit don't lead to the new position, so they can't be taken back
+    debug_assert_eq!(self.hash, self.calculate_hash(), "incremental hash drifted from the position");
+    debug_assert!(self.is_mailbox_in_sync(), "mailbox drifted from the bitboards");
+
+    let result = self.validate();
+    self.is_valid = result.is_ok();
+    if self.is_valid {
+      self.update_position_state();
+    }
+    else { // whatever was worked out for the last good position doesn't hold any more
+      self.checks.clear();
+      self.pinned_pieces = Bitboard::EMPTY;
+      self.pin_rays = [Bitboard::EMPTY; 64];
+      self.enemy_attacks = Bitboard::EMPTY;
+      self.castling_rights = CastlingRights { white_kingside: false, white_queenside: false, black_kingside: false, black_queenside: false };
+    }
+    result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::PieceKind;
+  use crate::board_representation::validation::PositionProblem;
+  use crate::bot::Bot;
+  use crate::game::Game;
+  use crate::pgn::GameResult;
+  use crate::board_representation::{GameStatus, IllegalMoveReason};
+
+  fn square(algebraic: &str) -> Square {
+    Square::from_algebraic(algebraic).unwrap()
+  }
+
+  #[test]
+  fn builds_the_same_board_as_a_fen() {
+    let mut board = Board::new("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    board.put_piece(square("h1"), Piece::new(Color::White, PieceKind::Rook)).unwrap();
+    board.put_piece(square("d7"), Piece::new(Color::Black, PieceKind::Pawn)).unwrap();
+    board.set_castling_rights(Color::White, true, true).unwrap();
+    board.remove_piece(square("d7")).unwrap();
+    board.put_piece(square("d5"), Piece::new(Color::Black, PieceKind::Pawn)).unwrap();
+    board.put_piece(square("e5"), Piece::new(Color::White, PieceKind::Pawn)).unwrap();
+    board.set_side_to_move(Color::White).unwrap();
+    board.set_en_passant(Some(square("d6"))).unwrap();
+
+    let fen = "4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1";
+    assert_eq!(board.to_fen(), fen);
+    assert_eq!(board.get_hash(), Board::new(fen).get_hash());
+    assert_eq!(board.get_all_moves().len(), Board::new(fen).get_all_moves().len());
+  }
+
+  #[test]
+  fn edits_can_pass_through_impossible_positions() {
+    let mut board = Board::new("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert_eq!(board.remove_piece(square("e8")).unwrap_err().get_problems(), [PositionProblem::WrongKingCount { color: Color::Black, count: 0 }]);
+    assert_eq!(board.remove_piece(square("a1")).unwrap_err().get_problems(), [
+      PositionProblem::WrongKingCount { color: Color::Black, count: 0 },
+      PositionProblem::ImpossibleCastling { color: Color::White, is_kingside: false },
+    ]);
+    assert!(board.set_castling_rights(Color::White, false, false).is_err());
+
+    // nothing can be played until the position is fixed
+    assert!(!board.is_valid());
+    assert!(board.get_all_moves().is_empty());
+    assert!(!board.make_null_move());
+    assert_eq!(Bot::new(true).get_best_move(&mut board), None);
+    assert_eq!(board.status(), GameStatus::ImpossiblePosition);
+    assert!(!board.is_checkmate());
+    assert_eq!(board.validate_move(square("h1"), square("h2"), None), Err(IllegalMoveReason::ImpossiblePosition));
+    assert_eq!(Game::new(board.clone()).get_result(), GameResult::Unknown);
+
+    // the side to move losing its king too
+    let mut kingless = board.clone();
+    assert!(kingless.remove_piece(square("e1")).is_err());
+    assert!(kingless.checkers().is_empty());
+    assert_eq!(kingless.status(), GameStatus::ImpossiblePosition);
+
+    board.put_piece(square("e8"), Piece::new(Color::Black, PieceKind::King)).unwrap();
+    assert!(board.is_valid());
+    assert_eq!(board.get_all_moves().len(), Board::new("4k3/8/8/8/8/8/8/4K2R w K - 0 1").get_all_moves().len());
+
+    assert_eq!(board.set_en_passant(Some(square("e6"))).unwrap_err().get_problems(), [PositionProblem::ImpossibleEnPassant]);
+    board.set_en_passant(None).unwrap();
+    assert_eq!(board.set_side_to_move(Color::Black), Ok(()));
+    assert_eq!(board.put_piece(square("e2"), Piece::new(Color::Black, PieceKind::Queen)).unwrap_err().get_problems(), [PositionProblem::OpponentInCheck]);
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/4q3/4K2R b K - 0 1");
+  }
+}
@@ -0,0 +1,462 @@
+use std::fmt;
+use crate::board_representation::{Board, CastlingFlags, CastlingRights, CastlingSquares, Bitboard, Square, PositionError};
+use crate::utils::{Color, PieceKind, Piece};
+
+// the six space separated fields of a FEN string, in order
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FenField {
+  Placement,
+  SideToMove,
+  Castling,
+  EnPassant,
+  HalfmoveClock,
+  FullmoveNumber
+}
+impl FenField {
+  const ALL: [FenField; 6] = [
+    FenField::Placement,
+    FenField::SideToMove,
+    FenField::Castling,
+    FenField::EnPassant,
+    FenField::HalfmoveClock,
+    FenField::FullmoveNumber
+  ];
+}
+impl fmt::Display for FenField {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let name = match self {
+      FenField::Placement => "piece placement",
+      FenField::SideToMove => "side to move",
+      FenField::Castling => "castling rights",
+      FenField::EnPassant => "en passant square",
+      FenField::HalfmoveClock => "halfmove clock",
+      FenField::FullmoveNumber => "fullmove number",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+// every offset is a character offset into the whole FEN string, not into the field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+  MissingField { field: FenField, offset: usize },
+  TooManyFields { offset: usize },
+  UnexpectedCharacter { field: FenField, character: char, offset: usize },
+  WrongRankLength { rank: usize, offset: usize }, // rank is counted from white's perspective (8 is the first one in the string)
+  WrongRankCount { ranks: usize, offset: usize },
+  ImpossibleCastling { offset: usize },
+  ImpossibleEnPassant { offset: usize },
+  InvalidClock { field: FenField, offset: usize },
+  InvalidPosition(PositionError), // it parsed, but the position itself is impossible
+}
+impl FenError {
+  pub fn field(&self) -> FenField {
+    match self {
+      FenError::MissingField { field, .. } => *field,
+      FenError::TooManyFields { .. } => FenField::FullmoveNumber,
+      FenError::UnexpectedCharacter { field, .. } => *field,
+      FenError::WrongRankLength { .. } => FenField::Placement,
+      FenError::WrongRankCount { .. } => FenField::Placement,
+      FenError::ImpossibleCastling { .. } => FenField::Castling,
+      FenError::ImpossibleEnPassant { .. } => FenField::EnPassant,
+      FenError::InvalidClock { field, .. } => *field,
+      FenError::InvalidPosition(_) => FenField::Placement,
+    }
+  }
+  pub fn offset(&self) -> usize {
+    match self {
+      FenError::MissingField { offset, .. } |
+      FenError::TooManyFields { offset } |
+      FenError::UnexpectedCharacter { offset, .. } |
+      FenError::WrongRankLength { offset, .. } |
+      FenError::WrongRankCount { offset, .. } |
+      FenError::ImpossibleCastling { offset } |
+      FenError::ImpossibleEnPassant { offset } |
+      FenError::InvalidClock { offset, .. } => *offset,
+      FenError::InvalidPosition(_) => 0, // the whole position is at fault, not one character of it
+    }
+  }
+}
+impl fmt::Display for FenError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FenError::MissingField { field, offset } => write!(f, "missing {} field at offset {}", field, offset),
+      FenError::TooManyFields { offset } => write!(f, "unexpected extra field at offset {}", offset),
+      FenError::UnexpectedCharacter { field, character, offset } => write!(f, "unexpected character '{}' in {} field at offset {}", character, field, offset),
+      FenError::WrongRankLength { rank, offset } => write!(f, "rank {} does not have exactly 8 columns (offset {})", rank, offset),
+      FenError::WrongRankCount { ranks, offset } => write!(f, "expected 8 ranks but found {} (offset {})", ranks, offset),
+      FenError::ImpossibleCastling { offset } => write!(f, "castling right without a king and rook on the back rank to castle with at offset {}", offset),
+      FenError::ImpossibleEnPassant { offset } => write!(f, "impossible en passant square at offset {}", offset),
+      FenError::InvalidClock { field, offset } => write!(f, "{} is not a number or is out of range (offset {})", field, offset),
+      FenError::InvalidPosition(error) => write!(f, "impossible position: {}", error),
+    }
+  }
+}
+impl std::error::Error for FenError {}
+
+pub fn piece_from_char(c: char) -> Option<Piece> { // uppercase is white
+  PieceKind::from_char(c).map(|kind| Piece::new(Color::from_is_white(c.is_ascii_uppercase()), kind))
+}
+
+pub fn char_from_piece(piece: Piece) -> char {
+  let c = piece.kind.to_char();
+  if piece.is_white() { c.to_ascii_uppercase() } else { c }
+}
+
+// splits the FEN into its fields, keeping the character offset each one starts at
+fn split_fields(fen: &str) -> Vec<(usize, Vec<char>)> {
+  let mut fields = Vec::new();
+  let mut current: Option<(usize, Vec<char>)> = None;
+
+  for (offset, c) in fen.chars().enumerate() {
+    if c.is_whitespace() {
+      if let Some(field) = current.take() {
+        fields.push(field);
+      }
+      continue;
+    }
+    current.get_or_insert((offset, Vec::new())).1.push(c);
+  }
+  if let Some(field) = current {
+    fields.push(field);
+  }
+
+  fields
+}
+
+impl Board {
+  pub(super) fn parse_fen(&mut self, fen: &str) -> Result<(), FenError> {
+    let fields = split_fields(fen);
+    let end_offset = fen.chars().count();
+
+    if fields.len() > FenField::ALL.len() {
+      return Err(FenError::TooManyFields { offset: fields[FenField::ALL.len()].0 });
+    }
+    if fields.len() < FenField::ALL.len() {
+      return Err(FenError::MissingField { field: FenField::ALL[fields.len()], offset: end_offset });
+    }
+
+    let (position_offset, position) = &fields[0];
+    let (side_to_move_offset, side_to_move) = &fields[1];
+    let (castling_offset, castling) = &fields[2];
+    let (en_passent_offset, en_passent_square) = &fields[3];
+    let (halfmove_offset, halfmove_clock) = &fields[4];
+    let (fullmove_offset, fullmove_num) = &fields[5];
+
+    // position
+    let mut x = 0;
+    let mut y = 7;
+    for (i, &c) in position.iter().enumerate() {
+      let offset = position_offset + i;
+      match c {
+        '/' => {
+          if x != 8 {
+            return Err(FenError::WrongRankLength { rank: y + 1, offset });
+          }
+          if y == 0 {
+            return Err(FenError::WrongRankCount { ranks: 9, offset });
+          }
+          y -= 1;
+          x = 0;
+        },
+        '1'..='8' => {
+          x += c.to_digit(10).unwrap() as usize;
+
+          if x > 8 {
+            return Err(FenError::WrongRankLength { rank: y + 1, offset });
+          }
+        },
+        _ => {
+          let Some(piece) = piece_from_char(c) else {
+            return Err(FenError::UnexpectedCharacter { field: FenField::Placement, character: c, offset });
+          };
+          if x >= 8 {
+            return Err(FenError::WrongRankLength { rank: y + 1, offset });
+          }
+          let square = Square::from_file_rank(x as u8, y as u8);
+          self.bitboards[piece.index()] |= square.bitboard();
+          self.mailbox[square.index()] = Some(piece);
+          x += 1;
+        },
+      }
+    }
+    let position_end = position_offset + position.len();
+    if x != 8 {
+      return Err(FenError::WrongRankLength { rank: y + 1, offset: position_end });
+    }
+    if y != 0 {
+      return Err(FenError::WrongRankCount { ranks: 8 - y, offset: position_end });
+    }
+
+    // side to move
+    self.white_to_move = match side_to_move.as_slice() {
+      ['w'] => true,
+      ['b'] => false,
+      _ => {
+        let (i, &c) = side_to_move.iter().enumerate().find(|(i, c)| *i > 0 || !matches!(c, 'w' | 'b')).unwrap();
+        return Err(FenError::UnexpectedCharacter { field: FenField::SideToMove, character: c, offset: side_to_move_offset + i });
+      }
+    };
+
+    // castling rights, KQkq means the outermost rook on that side of the king (X-FEN) and a file letter means the rook on that file (Shredder-FEN)
+    self.white_castling_flags = CastlingFlags { king_moved: true, rook_kingside_moved: true, rook_queenside_moved: true };
+    self.black_castling_flags = CastlingFlags { king_moved: true, rook_kingside_moved: true, rook_queenside_moved: true };
+    self.white_castling_squares = CastlingSquares::new(true);
+    self.black_castling_squares = CastlingSquares::new(false);
+    if castling.as_slice() != ['-'] {
+      let mut seen = Vec::new();
+      for (i, &c) in castling.iter().enumerate() {
+        let offset = castling_offset + i;
+        if !matches!(c.to_ascii_lowercase(), 'k' | 'q' | 'a'..='h') {
+          return Err(FenError::UnexpectedCharacter { field: FenField::Castling, character: c, offset });
+        }
+
+        let is_white = c.is_ascii_uppercase();
+        let color = Color::from_is_white(is_white);
+        let back_rank = if is_white { Bitboard::RANK_1 } else { Bitboard::RANK_8 };
+        let kings = self.pieces(color, PieceKind::King) & back_rank;
+        let (Some(king), 1) = (kings.first_square(), kings.count()) else {
+          return Err(FenError::ImpossibleCastling { offset });
+        };
+        let rooks = self.pieces(color, PieceKind::Rook) & back_rank;
+        let kingside_rooks: Bitboard = rooks.into_iter().filter(|rook| rook.file() > king.file()).collect();
+        let queenside_rooks: Bitboard = rooks.into_iter().filter(|rook| rook.file() < king.file()).collect();
+
+        let rook = match c.to_ascii_lowercase() {
+          'k' => kingside_rooks.first_square(), // the lowest square is the one nearest the h file
+          'q' => queenside_rooks.last_square(),
+          file => (rooks & Bitboard::file(file as u8 - b'a')).first_square(),
+        };
+        let Some(rook) = rook else {
+          return Err(FenError::ImpossibleCastling { offset });
+        };
+        let is_kingside = rook.file() > king.file();
+        if seen.contains(&(is_white, is_kingside)) {
+          return Err(FenError::UnexpectedCharacter { field: FenField::Castling, character: c, offset });
+        }
+        seen.push((is_white, is_kingside));
+
+        let (flags, squares) = if is_white {
+          (&mut self.white_castling_flags, &mut self.white_castling_squares)
+        } else {
+          (&mut self.black_castling_flags, &mut self.black_castling_squares)
+        };
+        let standard_squares = CastlingSquares::new(is_white);
+        let standard_rook = if is_kingside { standard_squares.kingside_rook } else { standard_squares.queenside_rook };
+        if king != standard_squares.king || rook != standard_rook {
+          self.is_chess960 = true;
+        }
+
+        squares.king = king;
+        flags.king_moved = false;
+        if is_kingside {
+          squares.kingside_rook = rook;
+          flags.rook_kingside_moved = false;
+        } else {
+          squares.queenside_rook = rook;
+          flags.rook_queenside_moved = false;
+        }
+      }
+    }
+    self.castling_rights = CastlingRights {
+      white_kingside: !self.white_castling_flags.rook_kingside_moved,
+      white_queenside: !self.white_castling_flags.rook_queenside_moved,
+      black_kingside: !self.black_castling_flags.rook_kingside_moved,
+      black_queenside: !self.black_castling_flags.rook_queenside_moved,
+    };
+
+    // en passent
+    self.en_passent_square = None;
+    if en_passent_square.as_slice() != ['-'] {
+      let file = match en_passent_square.first() {
+        Some(&c) if ('a'..='h').contains(&c) => c as u8 - b'a',
+        Some(&c) => return Err(FenError::UnexpectedCharacter { field: FenField::EnPassant, character: c, offset: *en_passent_offset }),
+        None => unreachable!(), // split_fields never makes empty fields
+      };
+      let rank = match en_passent_square.get(1) {
+        Some(&c) if ('1'..='8').contains(&c) => c as u8 - b'1',
+        Some(&c) => return Err(FenError::UnexpectedCharacter { field: FenField::EnPassant, character: c, offset: en_passent_offset + 1 }),
+        None => return Err(FenError::ImpossibleEnPassant { offset: *en_passent_offset }),
+      };
+      if let Some(&c) = en_passent_square.get(2) {
+        return Err(FenError::UnexpectedCharacter { field: FenField::EnPassant, character: c, offset: en_passent_offset + 2 });
+      }
+
+      let square = Square::from_file_rank(file, rank);
+      if !self.is_possible_en_passant(square) {
+        return Err(FenError::ImpossibleEnPassant { offset: *en_passent_offset });
+      }
+
+      self.en_passent_square = Some(square);
+    }
+
+    // halfmove_clock
+    self.halfmove_clock = parse_clock(halfmove_clock, FenField::HalfmoveClock, *halfmove_offset, 0)?;
+
+    // fullmove_num
+    self.fullmove_num = parse_clock(fullmove_num, FenField::FullmoveNumber, *fullmove_offset, 1)?; // the first move is move 1
+
+    Ok(())
+  }
+}
+
+fn parse_clock(field: &[char], field_type: FenField, offset: usize, min: u32) -> Result<i32, FenError> {
+  let text: String = field.iter().collect();
+  match text.parse::<u32>() {
+    Ok(value) if value >= min && value <= i32::MAX as u32 => Ok(value as i32),
+    _ => Err(FenError::InvalidClock { field: field_type, offset }),
+  }
+}
+
+impl Board {
+  pub fn to_fen(&self) -> String {
+    let mut fen = String::new();
+
+    // position
+    for y in (0..8).rev() {
+      let mut empty_squares = 0;
+      for x in 0..8 {
+        match self.piece_at(Square::from_file_rank(x, y)) {
+          Some(piece) => {
+            if empty_squares != 0 {
+              fen.push_str(&empty_squares.to_string());
+              empty_squares = 0;
+            }
+            fen.push(char_from_piece(piece));
+          },
+          None => empty_squares += 1,
+        }
+      }
+      if empty_squares != 0 {
+        fen.push_str(&empty_squares.to_string());
+      }
+      if y != 0 {
+        fen.push('/');
+      }
+    }
+
+    // side to move
+    fen.push_str(if self.white_to_move { " w " } else { " b " });
+
+    // castling rights
+    let mut castling = String::new();
+    let rights = [
+      (true, true, self.white_castling_flags.rook_kingside_moved, self.white_castling_squares.kingside_rook),
+      (true, false, self.white_castling_flags.rook_queenside_moved, self.white_castling_squares.queenside_rook),
+      (false, true, self.black_castling_flags.rook_kingside_moved, self.black_castling_squares.kingside_rook),
+      (false, false, self.black_castling_flags.rook_queenside_moved, self.black_castling_squares.queenside_rook),
+    ];
+    for (is_white, is_kingside, rook_moved, rook) in rights {
+      let (flags, squares) = if is_white { (self.white_castling_flags, self.white_castling_squares) } else { (self.black_castling_flags, self.black_castling_squares) };
+      if flags.king_moved || rook_moved {
+        continue;
+      }
+
+      // chess960 rooks that arent the outermost one on their side need their file written out
+      let rooks = self.pieces(Color::from_is_white(is_white), PieceKind::Rook) & Bitboard::rank(squares.king.rank());
+      let is_further_out = |other: &Square| if is_kingside { other.file() > rook.file() } else { other.file() < rook.file() };
+      let letter = if !rooks.into_iter().any(|other| is_further_out(&other)) {
+        if is_kingside { 'k' } else { 'q' }
+      } else {
+        (b'a' + rook.file()) as char
+      };
+      castling.push(if is_white { letter.to_ascii_uppercase() } else { letter });
+    }
+    if castling.is_empty() {
+      castling.push('-');
+    }
+    fen.push_str(&castling);
+
+    // en passent
+    match self.en_passent_square {
+      Some(square) => fen.push_str(&format!(" {}", square)),
+      None => fen.push_str(" -"),
+    }
+
+    // clocks
+    fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_num));
+
+    fen
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ROUND_TRIP_FENS: [&str; 13] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+    "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "4k3/8/8/8/8/8/8/4K2R w K - 37 95",
+    "8/8/8/8/8/8/6k1/4K3 b - - 99 140",
+    "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 3 20",
+    "1rk4r/8/8/8/8/8/8/1RK4R w KQkq - 0 1", // chess960
+    "1k1r1r2/8/8/8/8/8/8/1K1R1R2 w Dd - 0 1",
+  ];
+
+  #[test]
+  fn fen_round_trips() {
+    for fen in ROUND_TRIP_FENS {
+      let board = Board::from_fen(fen).unwrap();
+      assert_eq!(board.to_fen(), fen);
+      assert_eq!(Board::from_fen(&board.to_fen()).unwrap().to_fen(), fen);
+    }
+  }
+
+  #[test]
+  fn castling_field_decides_the_castles() {
+    let castles = |fen| -> Vec<String> {
+      Board::new(fen).get_all_moves().into_iter().filter(|m| m.is_castle()).map(|m| m.to_uci()).collect()
+    };
+    // the kings and rooks are all home, so only the field says whether they've moved
+    assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1"), Vec::<String>::new());
+    assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1"), ["e1g1"]);
+    assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1"), ["e8c8"]);
+    assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").len(), 2);
+  }
+
+  #[test]
+  fn fen_errors() {
+    use FenField::*;
+    let cases = [
+      // fields
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::MissingField { field: HalfmoveClock, offset: 52 }),
+      ("", FenError::MissingField { field: Placement, offset: 0 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 x", FenError::TooManyFields { offset: 57 }),
+      // placement
+      ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::WrongRankLength { rank: 7, offset: 16 }),
+      ("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::WrongRankLength { rank: 7, offset: 17 }),
+      ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::UnexpectedCharacter { field: Placement, character: '9', offset: 18 }),
+      ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::WrongRankCount { ranks: 7, offset: 41 }),
+      ("8/8/8/8/8/8/8/8/8 w - - 0 1", FenError::WrongRankCount { ranks: 9, offset: 15 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1", FenError::UnexpectedCharacter { field: Placement, character: 'X', offset: 42 }),
+      // side to move
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", FenError::UnexpectedCharacter { field: SideToMove, character: 'x', offset: 44 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR wb KQkq - 0 1", FenError::UnexpectedCharacter { field: SideToMove, character: 'b', offset: 45 }),
+      // castling
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkz - 0 1", FenError::UnexpectedCharacter { field: Castling, character: 'z', offset: 49 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKkq - 0 1", FenError::UnexpectedCharacter { field: Castling, character: 'K', offset: 47 }),
+      ("4k3/8/8/8/8/8/8/4K3 w K - 0 1", FenError::ImpossibleCastling { offset: 22 }),
+      // en passant
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1", FenError::ImpossibleEnPassant { offset: 51 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e 0 1", FenError::ImpossibleEnPassant { offset: 51 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z6 0 1", FenError::UnexpectedCharacter { field: EnPassant, character: 'z', offset: 51 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1", FenError::UnexpectedCharacter { field: EnPassant, character: '9', offset: 52 }),
+      // clocks
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", FenError::InvalidClock { field: HalfmoveClock, offset: 53 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 -1", FenError::InvalidClock { field: FullmoveNumber, offset: 55 }),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0", FenError::InvalidClock { field: FullmoveNumber, offset: 55 }),
+    ];
+
+    for (fen, expected) in cases {
+      let error = Board::from_fen(fen).err().unwrap_or_else(|| panic!("{} was accepted", fen));
+      assert_eq!(error, expected, "{}", fen);
+    }
+  }
+}
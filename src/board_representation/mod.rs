@@ -1,26 +1,37 @@
+pub mod bitboard;
 mod move_gen;
 mod precompiled_bitboards;
-
-use std::collections::HashMap;
+pub mod fen;
+mod perft;
+pub mod notation;
+pub mod chess960;
+pub mod attacks;
+pub mod validation;
+pub mod editing;
+mod zobrist;
+#[cfg(feature = "serde")]
+mod serialization;
+
+use std::fmt;
 use move_gen::*;
-use crate::utils::PieceType;
+use zobrist::*;
+pub use fen::FenError;
+pub use validation::{IllegalMoveReason, PositionError};
+pub use bitboard::{Bitboard, Square, Direction};
+use crate::utils::{Color, PieceKind, Piece};
 
-const EMPTY_VEC: Vec<Move> = Vec::new(); // have to store Vec::new() as a const as to allow for the copying of it
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
-const H1: u64 = 0x1;
-const A1: u64 = 0x80;
-const H8: u64 = 0x100000000000000;
-const A8: u64 = 0x8000000000000000;
 
-pub fn bits_to_indices(bitboard: &u64) -> Vec<i32> {
-  let mut indices = Vec::new();
-  for i in 0..64 {
-    if *bitboard & (1 << i) != 0 {
-      indices.push(i);
-    }
-  }
-  indices
-}
+const H1: Square = Square::from_file_rank(7, 0);
+const A1: Square = Square::from_file_rank(0, 0);
+const H8: Square = Square::from_file_rank(7, 7);
+const A8: Square = Square::from_file_rank(0, 7);
+const WHITE_KING_START: Square = Square::from_file_rank(4, 0);
+const BLACK_KING_START: Square = Square::from_file_rank(4, 7);
+// the files the king and rook end up on after castling, these are the same in chess960
+const KINGSIDE_CASTLE_FILES: (u8, u8) = (6, 5); // (king, rook)
+const QUEENSIDE_CASTLE_FILES: (u8, u8) = (2, 3);
 
 #[derive(Clone)]
 struct CastlingRights {
@@ -40,7 +51,7 @@ impl CastlingRights {
   }
 }
 
-#[derive(Clone)]
+#[derive(Copy, Clone)]
 struct CastlingFlags {
   king_moved: bool,
   rook_kingside_moved: bool,
@@ -57,81 +68,226 @@ impl CastlingFlags {
 }
 
 #[derive(Copy, Clone)]
-pub struct MoveFlags {
-  passented_square: Option<u64>,
-  can_be_en_passent: bool,
+struct CastlingSquares { // where the king and rooks start, only ever different from the normal squares in chess960
+  king: Square,
+  kingside_rook: Square,
+  queenside_rook: Square,
+}
+impl CastlingSquares {
+  fn new(is_white: bool) -> Self {
+    if is_white {
+      Self { king: WHITE_KING_START, kingside_rook: H1, queenside_rook: A1 }
+    }
+    else {
+      Self { king: BLACK_KING_START, kingside_rook: H8, queenside_rook: A8 }
+    }
+  }
+}
 
-  kingside_castle_square: Option<u64>,
-  queenside_castle_square: Option<u64>,
+// which of the legal moves get generated, so the search can look at captures before it bothers with anything else
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum GenerationStage {
+  Captures,
+  Quiets,
+  All
+}
 
+// what move generation knows about a piece's moves, which gets turned into a MoveKind for each one
+#[derive(Copy, Clone)]
+struct MoveFlags {
+  passented_square: Option<Square>,
+  can_be_en_passent: bool,
   is_promotion: bool,
 }
 impl MoveFlags {
-  pub fn new() -> Self {
+  fn new() -> Self {
     Self {
       passented_square: None,
       can_be_en_passent: false,
-  
-      kingside_castle_square: None,
-      queenside_castle_square: None,
-  
       is_promotion: false
     }
   }
 }
 
-#[derive(Copy, Clone)]
-pub struct Move {
-  pub start_square: i32,
-  pub end_square: i32,
-  moved_piece_type: PieceType,
-
-  // flags
-  captured_piece_type: Option<PieceType>,
-  flags: MoveFlags,
-  pub promotion_piece: Option<PieceType>
+// everything about a move that cant be worked out from the board and the two squares.
+// the 4 bit encoding from https://www.chessprogramming.org/Encoding_Moves, so the third bit is a capture and the fourth is a promotion
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+  Quiet,
+  DoublePush,
+  KingsideCastle,
+  QueensideCastle,
+  Capture,
+  EnPassant,
+  KnightPromotion = 8,
+  BishopPromotion,
+  RookPromotion,
+  QueenPromotion,
+  KnightPromotionCapture,
+  BishopPromotionCapture,
+  RookPromotionCapture,
+  QueenPromotionCapture
 }
+impl MoveKind {
+  // queen first, as it's nearly always the best one
+  const PROMOTIONS: [MoveKind; 4] = [MoveKind::QueenPromotion, MoveKind::KnightPromotion, MoveKind::BishopPromotion, MoveKind::RookPromotion];
+  const PROMOTION_CAPTURES: [MoveKind; 4] = [MoveKind::QueenPromotionCapture, MoveKind::KnightPromotionCapture, MoveKind::BishopPromotionCapture, MoveKind::RookPromotionCapture];
+
+  fn from_bits(bits: u16) -> Self {
+    match bits {
+      0 => MoveKind::Quiet,
+      1 => MoveKind::DoublePush,
+      2 => MoveKind::KingsideCastle,
+      3 => MoveKind::QueensideCastle,
+      4 => MoveKind::Capture,
+      5 => MoveKind::EnPassant,
+      8 => MoveKind::KnightPromotion,
+      9 => MoveKind::BishopPromotion,
+      10 => MoveKind::RookPromotion,
+      11 => MoveKind::QueenPromotion,
+      12 => MoveKind::KnightPromotionCapture,
+      13 => MoveKind::BishopPromotionCapture,
+      14 => MoveKind::RookPromotionCapture,
+      15 => MoveKind::QueenPromotionCapture,
+      _ => unreachable!("6 and 7 aren't move kinds")
+    }
+  }
+  pub fn is_capture(self) -> bool {
+    self as u16 & 0b0100 != 0
+  }
+  pub fn is_promotion(self) -> bool {
+    self as u16 & 0b1000 != 0
+  }
+  pub fn is_castle(self) -> bool {
+    matches!(self, MoveKind::KingsideCastle | MoveKind::QueensideCastle)
+  }
+}
+
+// the start square in the bottom 6 bits, the end square in the next 6 and the kind in the top 4.
+// the piece that moves and the piece that gets captured come from the board, so moves are small enough to keep lots of them around
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move(u16);
+const _: () = assert!(std::mem::size_of::<Move>() == 2);
 impl Move {
-  pub fn new(move_start_square: i32, move_end_square: i32, piece: PieceType, move_flags: MoveFlags) -> Self {
-    Self {
-      start_square: move_start_square,
-      end_square: move_end_square,
-      moved_piece_type: piece,
+  pub const NULL: Move = Move(0); // h1 to h1, which no piece can ever do, so it stands in for passing the turn
 
-      captured_piece_type: None,
-      flags: move_flags,
-      promotion_piece: None
+  pub fn new(start_square: Square, end_square: Square, kind: MoveKind) -> Self {
+    Self(start_square.index() as u16 | (end_square.index() as u16) << 6 | (kind as u16) << 12)
+  }
+  pub fn get_start_square(self) -> Square {
+    Square::new((self.0 & 0x3F) as u8)
+  }
+  pub fn get_end_square(self) -> Square {
+    Square::new((self.0 >> 6 & 0x3F) as u8)
+  }
+  pub fn get_kind(self) -> MoveKind {
+    MoveKind::from_bits(self.0 >> 12)
+  }
+  pub fn is_capture(self) -> bool {
+    self.get_kind().is_capture()
+  }
+  pub fn is_promotion(self) -> bool {
+    self.get_kind().is_promotion()
+  }
+  pub fn is_castle(self) -> bool {
+    self.get_kind().is_castle()
+  }
+  pub fn get_promotion_kind(self) -> Option<PieceKind> {
+    match self.get_kind() {
+      MoveKind::QueenPromotion | MoveKind::QueenPromotionCapture => Some(PieceKind::Queen),
+      MoveKind::BishopPromotion | MoveKind::BishopPromotionCapture => Some(PieceKind::Bishop),
+      MoveKind::KnightPromotion | MoveKind::KnightPromotionCapture => Some(PieceKind::Knight),
+      MoveKind::RookPromotion | MoveKind::RookPromotionCapture => Some(PieceKind::Rook),
+      _ => None
     }
   }
 }
-impl PartialEq for Move {
-  fn eq(&self, other: &Self) -> bool {
-    self.start_square == other.start_square && self.end_square == other.end_square && self.promotion_piece == other.promotion_piece
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+  Ongoing,
+  Checkmate(Color), // the winner
+  Stalemate,
+  InsufficientMaterial,
+  FiftyMove,
+  Repetition,
+  ImpossiblePosition // an edit left the board in a position that can't be played on, see Board::is_valid
+}
+impl GameStatus {
+  pub fn is_draw(&self) -> bool {
+    !matches!(self, GameStatus::Ongoing | GameStatus::Checkmate(_) | GameStatus::ImpossiblePosition)
   }
 }
+impl fmt::Display for GameStatus {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GameStatus::Ongoing => write!(f, "Game in progress"),
+      GameStatus::Checkmate(winner) => write!(f, "Checkmate, {} wins", winner),
+      GameStatus::Stalemate => write!(f, "Draw by stalemate"),
+      GameStatus::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+      GameStatus::FiftyMove => write!(f, "Draw by the fifty move rule"),
+      GameStatus::Repetition => write!(f, "Draw by threefold repetition"),
+      GameStatus::ImpossiblePosition => write!(f, "Impossible position"),
+    }
+  }
+}
+
+// everything make_move throws away, so that unmake_move can put it back
+#[derive(Clone)]
+struct UndoRecord {
+  move_made: Move,
+  captured_piece: Option<(Piece, Square)>, // not always on the end square because of en passent
+  castled_rook: Option<(Piece, Bitboard)>,
+  en_passent_square: Option<Square>,
+  white_castling_flags: CastlingFlags,
+  black_castling_flags: CastlingFlags,
+  halfmove_clock: i32,
+  fullmove_num: i32,
+  hash: u64,
+}
 
 #[derive(Clone)]
 pub struct Board {
-  bitboards: [u64; 12],
+  bitboards: [Bitboard; 12],
+  mailbox: [Option<Piece>; 64], // the same pieces as the bitboards, but indexed by square
   white_to_move: bool,
   castling_rights: CastlingRights,
-  en_passent_square: Option<u64>,
+  en_passent_square: Option<Square>,
   halfmove_clock: i32,
   fullmove_num: i32,
   white_castling_flags: CastlingFlags,
   black_castling_flags: CastlingFlags,
-
-  moves: [Vec<Move>; 64],
-  enemy_attacks: u64,
-  checks: Vec<u64>,
-  pinned_pieces: u64,
-  pin_rays: [u64; 64],
+  white_castling_squares: CastlingSquares,
+  black_castling_squares: CastlingSquares,
+  is_chess960: bool, // castles are written as the king taking its own rook, as the king can end up on a square it could walk to
+  hash: u64,
+  is_valid: bool, // false after an edit left the position impossible, nothing can be generated or played until another edit fixes it
+
+  enemy_attacks: Bitboard,
+  checks: Vec<Bitboard>,
+  pinned_pieces: Bitboard,
+  pin_rays: [Bitboard; 64],
+
+  undo_stack: Vec<UndoRecord>,
 }
 impl Board {
   /* BOARD SETUP */
-  pub fn new(fen: &str) -> Self {
+  pub fn new(fen: &str) -> Self { // only for FENs that are known to be good, use from_fen for anything that comes from a user
+    match Self::from_fen(fen) {
+      Ok(board) => board,
+      Err(error) => panic!("Invalid FEN: {}", error),
+    }
+  }
+  pub fn from_fen(fen: &str) -> Result<Self, FenError> { // switches to chess960 by itself if the castling rights need it
+    Self::from_fen_with_variant(fen, false)
+  }
+  pub fn from_fen_chess960(fen: &str) -> Result<Self, FenError> {
+    Self::from_fen_with_variant(fen, true)
+  }
+  fn from_fen_with_variant(fen: &str, is_chess960: bool) -> Result<Self, FenError> {
     let mut new_board = Self {
-      bitboards: [0; 12],
+      bitboards: [Bitboard::EMPTY; 12],
+      mailbox: [None; 64],
       white_to_move: true,
       castling_rights: CastlingRights::new(),
       en_passent_square: None,
@@ -139,217 +295,229 @@ impl Board {
       fullmove_num: 0,
       white_castling_flags: CastlingFlags::new(),
       black_castling_flags: CastlingFlags::new(),
+      white_castling_squares: CastlingSquares::new(true),
+      black_castling_squares: CastlingSquares::new(false),
+      is_chess960,
+      hash: 0,
+      is_valid: true,
 
-      moves: [EMPTY_VEC; 64],
-      enemy_attacks: 0,
+      enemy_attacks: Bitboard::EMPTY,
       checks: Vec::new(),
-      pinned_pieces: 0,
-      pin_rays: [0; 64],
+      pinned_pieces: Bitboard::EMPTY,
+      pin_rays: [Bitboard::EMPTY; 64],
+
+      undo_stack: Vec::new(),
     };
-    new_board.parse_fen(fen);
-    new_board.detect_check();
-    new_board.get_opponents_attacks();
-    new_board.castle_checks();
-    new_board.find_pinned_pieces();
-    new_board.get_all_legal_moves();
-    new_board
-  }
-  fn parse_fen(&mut self, fen: &str) {
-    let mut parts = fen.split(' ');
-    let position = parts.next().unwrap();
-    let side_to_move = parts.next().unwrap();
-    let _  = parts.next().unwrap(); // dont care about this part because thats not how my engine implements castling
-    let en_passent_square  = parts.next().unwrap();
-    let halfmove_clock  = parts.next().unwrap();
-    let fullmove_num  = parts.next().unwrap();
-
-    // position
-    let char_to_piecetype: HashMap<char, PieceType> = HashMap::from([
-      ('K', PieceType::WhiteKing),
-      ('Q', PieceType::WhiteQueen),
-      ('B', PieceType::WhiteBishop),
-      ('N', PieceType::WhiteKnight),
-      ('R', PieceType::WhiteRook),
-      ('P', PieceType::WhitePawn),
-      ('k', PieceType::BlackKing),
-      ('q', PieceType::BlackQueen),
-      ('b', PieceType::BlackBishop),
-      ('n', PieceType::BlackKnight),
-      ('r', PieceType::BlackRook),
-      ('p', PieceType::BlackPawn),
-    ]);
-    let mut x = 0;
-    let mut y = 7;
-    for c in position.chars() {
-      match c {
-        '/' => {
-          y -= 1;
-          x = 0;
-        },
-        '1'..='8' => {
-          x += c.to_digit(10).unwrap() as usize;
-          
-          if x > 8 {
-            panic!("Invalid FEN: rank {} does not have exactly 8 columns", 8 - x);
-          }
-        },
-        'P' | 'N' | 'B' | 'R' | 'K' | 'Q' | 'p' | 'n' | 'b' | 'r' | 'k' | 'q' => {
-          let bitboard_type = char_to_piecetype[&c];
-          let square_index = y * 8 + (7 - x); // oh my god this line of code took me like 30 minutes to figure out holy what the muffin | this isnt a really useful comment but it's kinda funny in my opinion
-          self.bitboards[bitboard_type as usize] |= 1 << square_index;
-          x += 1;
-        },
-        _ => panic!("Unexpected character in position field of FEN string"),
-      }
-    }
+    new_board.parse_fen(fen)?;
+    new_board.validate().map_err(FenError::InvalidPosition)?; // nothing past here can deal with a missing king
+    new_board.hash = new_board.calculate_hash();
+    new_board.update_position_state();
+    Ok(new_board)
+  }
+  fn update_position_state(&mut self) { // recalculates everything that comes from the pieces for the side to move
+    // detect_check looks for checks given by the side to move, so it has to look from the other side
+    self.white_to_move = !self.white_to_move;
+    self.detect_check();
+    self.white_to_move = !self.white_to_move;
 
-    // side to move
-    let side_to_move_chars: Vec<char> = side_to_move.chars().collect();
-    if side_to_move_chars.len() != 1 {
-      panic!("More than one character in side_to_move field of FEN string");
-    }
-    self.white_to_move = side_to_move_chars[0] == 'w';
-
-    // castling rights
-    self.white_castling_flags.king_moved = self.bitboards[PieceType::WhiteKing as usize] & 0x8 == 0;
-    self.white_castling_flags.rook_kingside_moved = self.bitboards[PieceType::WhiteRook as usize] & H1 == 0;
-    self.white_castling_flags.rook_queenside_moved = self.bitboards[PieceType::WhiteRook as usize] & A1 == 0;
-    self.black_castling_flags.king_moved = self.bitboards[PieceType::BlackKing as usize] & 0x800000000000000 == 0;
-    self.black_castling_flags.rook_kingside_moved = self.bitboards[PieceType::BlackRook as usize] & H8 == 0;
-    self.black_castling_flags.rook_queenside_moved = self.bitboards[PieceType::BlackRook as usize] & A8 == 0;
-
-    // en passent
-    if en_passent_square != "-" {
-      let en_passent_chars: Vec<char> = en_passent_square.chars().collect();
-      let char_to_int: HashMap<char, i32> = HashMap::from([
-        ('h', 0),
-        ('g', 1),
-        ('f', 2),
-        ('e', 3),
-        ('d', 4),
-        ('c', 5),
-        ('b', 6),
-        ('a', 7)
-      ]);
-      
-      let mut square = char_to_int[&en_passent_chars[0]];
-      if let Some(square_num) = en_passent_chars[1].to_digit(10) {
-        square += (square_num as i32) * 8;
-      }
+    self.find_pinned_pieces();
+    self.get_opponents_attacks();
+    self.castle_checks();
+  }
+  /* HELPER FUNCTIONS */
+  fn pieces(&self, color: Color, kind: PieceKind) -> Bitboard {
+    self.bitboards[Piece::new(color, kind).index()]
+  }
+  fn pieces_of_kind(&self, kind: PieceKind) -> Bitboard { // both colours
+    self.pieces(Color::White, kind) | self.pieces(Color::Black, kind)
+  }
+  fn all_pieces(&self, color: Color) -> Bitboard {
+    PieceKind::ALL.iter().fold(Bitboard::EMPTY, |all, kind| all | self.pieces(color, *kind))
+  }
+  fn all_white_pieces(&self) -> Bitboard {
+    self.all_pieces(Color::White)
+  }
+  fn all_black_pieces(&self) -> Bitboard {
+    self.all_pieces(Color::Black)
+  }
+  fn side_to_move(&self) -> Color {
+    Color::from_is_white(self.white_to_move)
+  }
+  fn enemy_sliders(&self) -> (Bitboard, Bitboard) { // the orthogonal and diagonal sliders of the side not to move, queens are both
+    let enemy = !self.side_to_move();
+    let queens = self.pieces(enemy, PieceKind::Queen);
+    (queens | self.pieces(enemy, PieceKind::Rook), queens | self.pieces(enemy, PieceKind::Bishop))
+  }
+  fn toggle_bitboard(&mut self, piece: Piece, squares: Bitboard) { // flips the squares on the bitboard and keeps the mailbox in sync
+    self.bitboards[piece.index()] ^= squares;
 
-      self.en_passent_square = Some(1 << square);
+    for square in squares {
+      if self.bitboards[piece.index()].contains(square) {
+        self.mailbox[square.index()] = Some(piece);
+      }
+      else if self.mailbox[square.index()] == Some(piece) { // a chess960 castle can put the king where its rook was before the rook is moved
+        self.mailbox[square.index()] = None;
+      }
     }
-
-    // halfmove_clock
-    self.halfmove_clock = halfmove_clock.parse().unwrap();
-
-    // fullmove_num
-    self.fullmove_num = fullmove_num.parse().unwrap();
   }
+  fn toggle_pieces(&mut self, piece: Piece, squares: Bitboard) { // toggle_bitboard, but it keeps the hash in sync too
+    self.toggle_bitboard(piece, squares);
 
-  /* HELPER FUNCTIONS */
-  fn all_white_pieces(&self) -> u64 {
-    self.bitboards[PieceType::WhiteKing as usize] | self.bitboards[PieceType::WhiteQueen as usize] | self.bitboards[PieceType::WhiteBishop as usize] | self.bitboards[PieceType::WhiteKnight as usize] | self.bitboards[PieceType::WhiteRook as usize] | self.bitboards[PieceType::WhitePawn as usize]
+    for square in squares {
+      self.hash ^= piece_key(piece, square);
+    }
+  }
+  fn is_mailbox_in_sync(&self) -> bool {
+    Square::all().all(|square| {
+      let bitboard_piece = Piece::iter().find(|piece| self.bitboards[piece.index()].contains(square));
+      self.mailbox[square.index()] == bitboard_piece
+    })
   }
-  fn all_black_pieces(&self) -> u64 {
-    self.bitboards[PieceType::BlackKing as usize] | self.bitboards[PieceType::BlackQueen as usize] | self.bitboards[PieceType::BlackBishop as usize] | self.bitboards[PieceType::BlackKnight as usize] | self.bitboards[PieceType::BlackRook as usize] | self.bitboards[PieceType::BlackPawn as usize]
+  fn are_squares_attacked(&self, squares: Bitboard) -> bool {
+    !(self.enemy_attacks & squares).is_empty()
   }
-  fn are_squares_attacked(&self, squares: u64) -> bool {
-    self.enemy_attacks & squares != 0
+  fn king_square(&self, is_white: bool) -> Square {
+    self.pieces(Color::from_is_white(is_white), PieceKind::King).first_square().expect("both kings are always on the board")
   }
   // getters
-  pub fn get_bitboards(&self) -> [u64; 12] {
+  pub fn get_bitboards(&self) -> [Bitboard; 12] {
     self.bitboards
   }
+  pub fn piece_at(&self, square: Square) -> Option<Piece> {
+    self.mailbox[square.index()]
+  }
   pub fn get_if_white_to_move(&self) -> bool {
     self.white_to_move
   }
-  pub fn get_moves(&self, index: i32) -> &Vec<Move> {
-    &self.moves[index as usize]
+  pub fn is_chess960(&self) -> bool {
+    self.is_chess960
+  }
+  pub fn get_hash(&self) -> u64 { // zobrist key, two boards with the same pieces, side to move, castling and en passent file have the same one
+    self.hash
+  }
+  pub fn is_valid(&self) -> bool { // whether the last edit left a position that can be played on, see editing.rs
+    self.is_valid
+  }
+  pub fn is_in_check(&self) -> bool {
+    !self.checks.is_empty()
+  }
+  pub fn get_moves(&self, square: Square) -> Vec<Move> { // the legal moves of the piece on the square
+    let mut moves = Vec::new();
+    self.generate_moves(&mut moves);
+    moves.retain(|m| m.get_start_square() == square);
+    moves
   }
   pub fn get_all_moves(&self) -> Vec<Move> {
     let mut moves = Vec::new();
+    self.generate_moves(&mut moves);
+    moves
+  }
 
-    for piece_moves in &self.moves {
-      for piece_move in piece_moves {
-        moves.push(piece_move.clone());
-      }
+  /* STAGED MOVE GEN */
+  // these all add to the end of the buffer instead of clearing it, so it can be reused or filled a stage at a time.
+  // captures and quiets never overlap, and between them they're every legal move
+  pub fn generate_moves(&self, moves: &mut Vec<Move>) {
+    self.generate_legal_moves(GenerationStage::All, moves);
+  }
+  pub fn generate_captures(&self, moves: &mut Vec<Move>) { // en passent and promotions that take something count as captures
+    self.generate_legal_moves(GenerationStage::Captures, moves);
+  }
+  pub fn generate_quiets(&self, moves: &mut Vec<Move>) { // castles and promotions onto an empty square count as quiet
+    self.generate_legal_moves(GenerationStage::Quiets, moves);
+  }
+  // the king moves and the blocks and captures of the checker, which is every legal move when in check. when not in check there's nothing to evade,
+  // so nothing is added
+  pub fn generate_evasions(&self, moves: &mut Vec<Move>) {
+    if !self.is_in_check() {
+      return;
     }
-
-    moves
+    self.generate_legal_moves(GenerationStage::All, moves); // the check mask already keeps out anything that doesn't deal with the check
   }
 
   /* MOVE GEN */
   fn get_opponents_attacks(&mut self) {
-    self.enemy_attacks = 0;
+    self.enemy_attacks = Bitboard::EMPTY;
 
-    for piece_type in PieceType::get_colour_types(!self.white_to_move) {
-      for square in bits_to_indices(&self.bitboards[piece_type as usize]) {
-        self.enemy_attacks |= self.get_legal_moves(square, piece_type, true).0;
+    for kind in PieceKind::ALL {
+      let piece = Piece::new(!self.side_to_move(), kind);
+      for square in self.bitboards[piece.index()] {
+        let attacks = self.get_legal_moves(square, piece, true).0;
+        self.enemy_attacks |= attacks;
       }
     }
   }
-  fn castle_checks(&mut self) {
-    if !self.white_castling_flags.king_moved {
-      self.castling_rights.white_kingside = !self.white_castling_flags.rook_kingside_moved && self.all_white_pieces() & 0x6 == 0 && self.are_squares_attacked(0x6);       
-      self.castling_rights.white_queenside = !self.white_castling_flags.rook_queenside_moved && self.all_white_pieces() & 0x70 == 0 && self.are_squares_attacked(0x70);
-    }
-    else {
-      self.castling_rights.white_kingside = false;
-      self.castling_rights.white_queenside = false;
+  fn castle_squares(&self, is_white: bool, is_kingside: bool) -> (Square, Square, Square, Square) { // king start, rook start, king end, rook end
+    let squares = if is_white { self.white_castling_squares } else { self.black_castling_squares };
+    let (king_file, rook_file) = if is_kingside { KINGSIDE_CASTLE_FILES } else { QUEENSIDE_CASTLE_FILES };
+    let rook_start = if is_kingside { squares.kingside_rook } else { squares.queenside_rook };
+    let back_rank = squares.king.rank();
+
+    (squares.king, rook_start, Square::from_file_rank(king_file, back_rank), Square::from_file_rank(rook_file, back_rank))
+  }
+  fn has_castling_right(&self, is_kingside: bool) -> bool { // for the side to move, whether or not it can castle right now
+    let flags = if self.white_to_move { self.white_castling_flags } else { self.black_castling_flags };
+    !flags.king_moved && if is_kingside { !flags.rook_kingside_moved } else { !flags.rook_queenside_moved }
+  }
+  fn castling_flags_mut(&mut self, color: Color) -> &mut CastlingFlags {
+    if color.is_white() { &mut self.white_castling_flags } else { &mut self.black_castling_flags }
+  }
+  fn rook_left_square(&mut self, color: Color, square: Square) { // a rook moving or being taken from where it castles from loses that castle
+    let squares = if color.is_white() { self.white_castling_squares } else { self.black_castling_squares };
+    let flags = self.castling_flags_mut(color);
+    flags.rook_kingside_moved |= square == squares.kingside_rook;
+    flags.rook_queenside_moved |= square == squares.queenside_rook;
+  }
+  fn can_castle(&self, is_kingside: bool) -> bool {
+    self.is_castling_path_clear(is_kingside) && self.is_castling_path_safe(is_kingside)
+  }
+  fn is_castling_path_clear(&self, is_kingside: bool) -> bool { // everything the king and rook cross has to be empty, apart from the king and rook themselves
+    let (king_start, rook_start, king_end, rook_end) = self.castle_squares(self.white_to_move, is_kingside);
+
+    let occupancy = (self.all_white_pieces() | self.all_black_pieces()) ^ king_start.bitboard() ^ rook_start.bitboard();
+    let path = squares_between(king_start, king_end) | king_end.bitboard() | squares_between(rook_start, rook_end) | rook_end.bitboard();
+    (occupancy & path).is_empty()
+  }
+  fn is_castling_path_safe(&self, is_kingside: bool) -> bool {
+    let (king_start, rook_start, king_end, _) = self.castle_squares(self.white_to_move, is_kingside);
+    let occupancy = (self.all_white_pieces() | self.all_black_pieces()) ^ king_start.bitboard() ^ rook_start.bitboard();
+
+    // the king cant castle out of, through or into check
+    if self.are_squares_attacked(squares_between(king_start, king_end) | king_start.bitboard() | king_end.bitboard()) {
+      return false;
     }
-  
-    if !self.black_castling_flags.king_moved {
-      self.castling_rights.black_kingside = !self.black_castling_flags.rook_kingside_moved && self.all_black_pieces() & 0x600000000000000 == 0 && self.are_squares_attacked(0x600000000000000);
-      self.castling_rights.black_queenside = !self.black_castling_flags.rook_queenside_moved && self.all_black_pieces() & 0x7000000000000000 == 0 && self.are_squares_attacked(0x7000000000000000);
+    // in chess960 the rook can be what was stopping a rook or queen from seeing where the king ends up
+    let (orthogonal_sliders, _) = self.enemy_sliders();
+    (get_rook_moves(king_end, occupancy) & orthogonal_sliders).is_empty()
+  }
+  fn castle_checks(&mut self) { // only the side to move gets castling rights, that way the enemy king's attacks never include a castle
+    let kingside = self.has_castling_right(true) && self.can_castle(true);
+    let queenside = self.has_castling_right(false) && self.can_castle(false);
+    if self.white_to_move {
+      self.castling_rights = CastlingRights { white_kingside: kingside, white_queenside: queenside, black_kingside: false, black_queenside: false };
     }
     else {
-      self.castling_rights.black_kingside = false;
-      self.castling_rights.black_queenside = false;
+      self.castling_rights = CastlingRights { white_kingside: false, white_queenside: false, black_kingside: kingside, black_queenside: queenside };
     }
-    
   }
-  fn detect_check(&mut self) {
+  fn detect_check(&mut self) { // finds every check the side to move is giving the enemy king
     self.checks = Vec::new();
 
-    for i in 0..63 {
-      for piece_type in PieceType::get_colour_types(self.white_to_move) {
-        if 1 << i & self.bitboards[piece_type as usize] == 0 {
-          continue;
-        }
-        let moves_bitboards = self.get_legal_moves(i, piece_type, false).0;
-        let enemy_king = if self.white_to_move { self.bitboards[PieceType::BlackKing as usize] } else { self.bitboards[PieceType::WhiteKing as usize] };
-        let check_move = moves_bitboards & enemy_king; // if the move is capturing the king
-        if check_move == 0 {
+    let king_square = self.king_square(!self.white_to_move);
+
+    for kind in PieceKind::ALL {
+      let piece = Piece::new(self.side_to_move(), kind);
+      for square in self.bitboards[piece.index()] {
+        let attacks = self.get_legal_moves(square, piece, true).0;
+        if !attacks.contains(king_square) {
           continue;
         }
-        match piece_type {
-          PieceType::WhiteKnight | PieceType::BlackKnight | PieceType::WhitePawn | PieceType::BlackPawn => {
-            self.checks.push(1 << i);
+        match kind {
+          PieceKind::Knight | PieceKind::Pawn => {
+            self.checks.push(square.bitboard());
+          },
+          PieceKind::Queen | PieceKind::Bishop | PieceKind::Rook => {
+            self.checks.push(squares_between(square, king_square) | square.bitboard()); // the check can be blocked anywhere along the ray
           },
-          PieceType::WhiteQueen | PieceType::BlackQueen | PieceType::WhiteBishop | PieceType::BlackBishop | PieceType::WhiteRook | PieceType::BlackRook  => {
-            let delta = i - enemy_king.trailing_zeros() as i32;
-            let mut direction = match delta {
-              d if d % 8 == 0 => 8, // vertical
-              d if d % 7 == 0 => 7, // diagonal /
-              d if d % 9 == 0 => 9, // diagonal \
-              d if d.abs() < 8 => 1, // horizontal
-              _ => 0,
-            };
-            if delta > 0 {
-              direction *= -1;
-            }
-            let mut ray = 0;
-            let mut pos = i as i32;
-            while pos >= 0 && pos < 64 {
-              if pos == enemy_king.trailing_zeros() as i32 {
-                break;
-              }
-              ray |= 1 << pos;
-              pos += direction;
-            }
-            self.checks.push(ray | (1 << i));
-          }
           _ => {
             // do nothin
           }
@@ -358,548 +526,493 @@ impl Board {
     }
   }
   fn find_pinned_pieces(&mut self) {
-    self.pinned_pieces = 0;
-    self.pin_rays = [0; 64];
+    self.pinned_pieces = Bitboard::EMPTY;
+    self.pin_rays = [Bitboard::EMPTY; 64];
 
-    let king = if self.white_to_move { self.bitboards[PieceType::WhiteKing as usize] } else { self.bitboards[PieceType::BlackKing as usize] };
-    let king_square = king.trailing_zeros() as i32;
-    let sliders = if self.white_to_move {
-      [PieceType::BlackQueen, PieceType::BlackRook, PieceType::BlackQueen, PieceType::BlackBishop]
-    } else {
-      [PieceType::WhiteQueen, PieceType::WhiteRook, PieceType::WhiteQueen, PieceType::WhiteBishop]
-    };
-    let orthogonal_rays = get_rook_moves(king_square, &0);
-    let diagonal_rays = get_bishop_moves(king_square, &0);
-    
-    for (i, slider_type) in sliders.iter().enumerate() {
-      let is_diagonal = i > 1; // if the itteration count is past the second one, its diagonal
+    let king_square = self.king_square(self.white_to_move);
+    let (orthogonal_sliders, diagonal_sliders) = self.enemy_sliders();
+    // only sliders that would see the king on an empty board can pin anything
+    let pinners = (orthogonal_sliders & get_rook_moves(king_square, Bitboard::EMPTY)) | (diagonal_sliders & get_bishop_moves(king_square, Bitboard::EMPTY));
+
+    let occupancy = self.all_white_pieces() | self.all_black_pieces();
+    let friendly_occupation = if self.white_to_move { self.all_white_pieces() } else { self.all_black_pieces() };
 
-      let slider_type_bitboard = self.bitboards[*slider_type as usize];
-      if slider_type_bitboard & (orthogonal_rays | diagonal_rays) == 0 {
+    for pinner_square in pinners {
+      let ray = squares_between(king_square, pinner_square);
+      let blockers = ray & occupancy;
+      if blockers.count() != 1 || (blockers & friendly_occupation).is_empty() { // has to be exactly one piece in the way, and it has to be ours
         continue;
       }
-      
-      for i in 0..63 {
-        let piece_bitboard = 1 << i;
-        if piece_bitboard & slider_type_bitboard == 0 {
-          continue;
-        }
-        
-        let delta = king_square - i;
-        let directional_mask = if delta < 0 {
-          if is_diagonal {
-            if delta % 9 == 0 {
-              // up left
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & (TOP_RANK | LEFT_FILE) == 0 {
-                square = square << 9;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-          
-              mask
-            }
-            else if delta % 7 == 0 {
-              // up right
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & (TOP_RANK | RIGHT_FILE) == 0 {
-                square = square << 7;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-          
-              mask
-            }
-            else {
-              0
-            }
-          }
-          else {
-            if delta > 7 {
-              // up
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & TOP_RANK == 0 {
-                square = square << 8;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-            
-              mask
-            }
-            else {
-              // left
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & LEFT_FILE == 0 {
-                square = square << 1;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-            
-              mask
-            }
-          }
-        }
-        else {
-          if is_diagonal {
-            if delta.abs() % 9 == 0 {
-              // down right
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & (BOTTOM_RANK | RIGHT_FILE) == 0 {
-                square = square >> 9;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-          
-              mask
-            }
-            else if delta.abs() % 7 == 0{
-              // down left
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & (BOTTOM_RANK | LEFT_FILE) == 0 {
-                square = square >> 7;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-          
-              mask
-            }
-            else {
-              0
-            }
-          }
-          else {
-            if delta.abs() > 7 {
-              // down
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & BOTTOM_RANK == 0 {
-                square = square >> 8;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-            
-              mask
-            }
-            else {
-              // right
-              let mut mask = 0;
-              let mut square = king;
-          
-              while square & RIGHT_FILE == 0 {
-                square = square >> 1;
-                if square & piece_bitboard != 0 {
-                  break;
-                }
-
-                mask |= square;
-              }
-            
-              mask
-            }
-          }
-        };
-        let rays = if is_diagonal { diagonal_rays } else { orthogonal_rays };
-        let ray = rays & directional_mask;
-        
-        let enemy_occupation = if self.white_to_move { self.all_black_pieces() } else { self.all_white_pieces() };
-        if enemy_occupation & ray != 0 {
-          continue;
-        }
-        
-        let friendly_occupation = if self.white_to_move { self.all_white_pieces() } else { self.all_black_pieces() };
-        let friendly_blockers = friendly_occupation & ray;
-        if friendly_blockers.count_ones() != 1 {
-          continue;
-        }
 
-        self.pinned_pieces |= friendly_blockers;
-        self.pin_rays[friendly_blockers.trailing_zeros() as usize] = ray | piece_bitboard;
-      }
+      self.pinned_pieces |= blockers;
+      self.pin_rays[blockers.first_square().unwrap().index()] = ray | pinner_square.bitboard();
     }
   }
+  fn is_en_passent_legal(&self, start_square: Square, end_square: Square) -> bool { // taking en passent removes two pieces from the same rank at once, which pins cant see
+    let passented_square = end_square.bitboard().shift(Direction::forward(!self.white_to_move));
+    let occupancy = ((self.all_white_pieces() | self.all_black_pieces()) ^ start_square.bitboard() ^ passented_square) | end_square.bitboard();
 
-  fn generate_moves_from_bitboard(&self, piece_square: i32, moves_bitboard: u64, piece_type: PieceType, flags: MoveFlags) -> Vec<Move>{
-    let mut moves: Vec<Move> = Vec::new();
-
-    for square in bits_to_indices(&moves_bitboard) {
-      let mut new_move = Move::new(piece_square, square, piece_type, flags);
+    let (orthogonal_sliders, diagonal_sliders) = self.enemy_sliders();
+    let king_square = self.king_square(self.white_to_move);
+    (get_rook_moves(king_square, occupancy) & orthogonal_sliders).is_empty() && (get_bishop_moves(king_square, occupancy) & diagonal_sliders).is_empty()
+  }
 
-      for piece_type in PieceType::iter() {
-        if self.bitboards[piece_type as usize] & 1 << square != 0 { // if the square already has something on it
-          new_move.captured_piece_type = Some(piece_type);
-        }
-      }
-      if new_move.captured_piece_type == Some(PieceType::WhiteKing) || new_move.captured_piece_type == Some(PieceType::BlackKing) {
+  fn generate_moves_from_bitboard(&self, piece_square: Square, moves_bitboard: Bitboard, flags: MoveFlags, moves: &mut Vec<Move>) {
+    for square in moves_bitboard {
+      let captured_piece = self.mailbox[square.index()];
+      if captured_piece.is_some_and(|piece| piece.kind == PieceKind::King) {
         continue;
-      } 
+      }
+      let is_capture = captured_piece.is_some();
 
       if flags.is_promotion {
-        if self.white_to_move {
-          new_move.promotion_piece = Some(PieceType::WhiteQueen);
-          moves.push(new_move.clone());
-          new_move.promotion_piece = Some(PieceType::WhiteKnight);
-          moves.push(new_move.clone());
-          new_move.promotion_piece = Some(PieceType::WhiteBishop);
-          moves.push(new_move.clone());
-          new_move.promotion_piece = Some(PieceType::WhiteRook);
-          moves.push(new_move.clone());
+        for kind in if is_capture { MoveKind::PROMOTION_CAPTURES } else { MoveKind::PROMOTIONS } {
+          moves.push(Move::new(piece_square, square, kind));
         }
-        else {
-          new_move.promotion_piece = Some(PieceType::BlackQueen);
-          moves.push(new_move.clone());
-          new_move.promotion_piece = Some(PieceType::BlackKnight);
-          moves.push(new_move.clone());
-          new_move.promotion_piece = Some(PieceType::BlackBishop);
-          moves.push(new_move.clone());
-          new_move.promotion_piece = Some(PieceType::BlackRook);
-          moves.push(new_move.clone());
+        continue;
+      }
+
+      let kind = if is_capture {
+        MoveKind::Capture
+      }
+      else if flags.can_be_en_passent && self.en_passent_square == Some(square) {
+        if !self.is_en_passent_legal(piece_square, square) {
+          continue;
         }
+        MoveKind::EnPassant
       }
-      else {
-        moves.push(new_move);
+      else if flags.passented_square == Some(square) {
+        MoveKind::DoublePush
       }
+      else {
+        MoveKind::Quiet
+      };
+      moves.push(Move::new(piece_square, square, kind));
     }
-
-    moves
   }
-  fn get_legal_moves(&mut self, square_index: i32, piece_type: PieceType, only_attacks: bool) -> (u64, MoveFlags) {
-    let mut moves = 0;
+  fn get_legal_moves(&self, square_index: Square, piece: Piece, only_attacks: bool) -> (Bitboard, MoveFlags) {
+    let mut moves;
     let mut flags = MoveFlags::new();
 
-    let bitboard = 1 << square_index;
+    let bitboard = square_index.bitboard();
     let occupancy = self.all_white_pieces() | self.all_black_pieces();
+    let is_white = piece.is_white();
+    let own_pieces = self.all_pieces(piece.color);
+    let enemy_pieces = self.all_pieces(!piece.color);
+    // attacks go through the enemy king, so it cant step backwards along the ray it's being checked on
+    let slider_occupancy = if only_attacks { occupancy ^ self.pieces(!piece.color, PieceKind::King) } else { occupancy };
 
-    match piece_type {
-      PieceType::WhiteKing => {
-        
-        (moves, flags.kingside_castle_square, flags.queenside_castle_square) = king_moves(&bitboard, self.castling_rights.white_kingside, self.castling_rights.white_queenside);
-        
-        if !only_attacks {
-          moves ^= moves & self.all_white_pieces(); 
-          moves ^= moves & self.enemy_attacks;
-        }
-      },
-      PieceType::BlackKing => {
-        (moves, flags.kingside_castle_square, flags.queenside_castle_square) = king_moves(&bitboard, self.castling_rights.black_kingside, self.castling_rights.black_queenside);
+    match piece.kind {
+      PieceKind::King => {
+        moves = king_moves(bitboard);
 
         if !only_attacks {
-          moves ^= moves & self.all_black_pieces(); 
-          moves ^= moves & self.enemy_attacks;
+          moves &= !self.enemy_attacks;
         }
       },
-      PieceType::WhiteQueen => {
-        let diagonal_moves= if !only_attacks {
-          get_bishop_moves(square_index, &occupancy)
-        } else {
-          get_bishop_moves(square_index, &(occupancy ^ self.bitboards[PieceType::BlackKing as usize]))
-        };
-        let orthogonal_moves = if !only_attacks {
-          get_rook_moves(square_index, &occupancy)
-        } else {
-          get_rook_moves(square_index, &(occupancy ^ self.bitboards[PieceType::BlackKing as usize]))
-        };
-        moves = diagonal_moves | orthogonal_moves;
-
-        if !only_attacks {
-          moves ^= moves & self.all_white_pieces(); 
-        }
-      },
-      PieceType::BlackQueen => {
-        let diagonal_moves = if !only_attacks {
-          get_bishop_moves(square_index, &occupancy)
-        } else {
-          get_bishop_moves(square_index, &(occupancy ^ self.bitboards[PieceType::WhiteKing as usize]))
-        };
-        let orthogonal_moves = if !only_attacks {
-          get_rook_moves(square_index, &occupancy)
-        } else {
-          get_rook_moves(square_index, &(occupancy ^ self.bitboards[PieceType::WhiteKing as usize]))
-        };
-        moves = diagonal_moves | orthogonal_moves;
-
-        if !only_attacks {
-          moves ^= moves & self.all_black_pieces(); 
-        }
+      PieceKind::Queen => {
+        moves = get_bishop_moves(square_index, slider_occupancy) | get_rook_moves(square_index, slider_occupancy);
       },
-      PieceType::WhiteBishop => {
-        moves = if !only_attacks {
-          get_bishop_moves(square_index, &occupancy)
-        } else {
-          get_bishop_moves(square_index, &(occupancy ^ self.bitboards[PieceType::BlackKing as usize]))
-        };
-        
-        if !only_attacks {
-          moves ^= moves & self.all_white_pieces(); 
-        }
+      PieceKind::Bishop => {
+        moves = get_bishop_moves(square_index, slider_occupancy);
       },
-      PieceType::BlackBishop => {
-        moves = if !only_attacks {
-          get_bishop_moves(square_index, &occupancy)
-        } else {
-          get_bishop_moves(square_index, &(occupancy ^ self.bitboards[PieceType::WhiteKing as usize]))
-        };
-
-        if !only_attacks {
-          moves ^= moves & self.all_black_pieces(); 
-        }
+      PieceKind::Knight => {
+        moves = knight_moves(bitboard);
       },
-      PieceType::WhiteKnight => {
-        moves = knight_moves(&bitboard);
-
-        if !only_attacks {
-          moves ^= moves & self.all_white_pieces(); 
-        }
+      PieceKind::Rook => {
+        moves = get_rook_moves(square_index, slider_occupancy);
       },
-      PieceType::BlackKnight => {
-        moves = knight_moves(&bitboard);
-
-        if !only_attacks {
-          moves ^= moves & self.all_black_pieces(); 
-        }
-      },
-      PieceType::WhiteRook => {
-        moves = if !only_attacks {
-          get_rook_moves(square_index, &occupancy)
-        } else {
-          get_rook_moves(square_index, &(occupancy ^ self.bitboards[PieceType::BlackKing as usize]))
-        };
-
-        if !only_attacks {
-          moves ^= moves & self.all_white_pieces(); 
-        }
-      },
-      PieceType::BlackRook => {
-        moves = if !only_attacks {
-          get_rook_moves(square_index, &occupancy)
-        } else {
-          get_rook_moves(square_index, &(occupancy ^ self.bitboards[PieceType::WhiteKing as usize]))
-        };
-
-        if !only_attacks {
-          moves ^= moves & self.all_black_pieces(); 
-        }
-      },
-      PieceType::WhitePawn => {
+      PieceKind::Pawn => {
         let mut attacks;
         let mut is_move_promotion = false;
         let is_attack_promotion;
 
-        (attacks, flags.can_be_en_passent, is_attack_promotion) = pawn_attacks(&bitboard, true, self.en_passent_square);
+        moves = Bitboard::EMPTY;
+        (attacks, flags.can_be_en_passent, is_attack_promotion) = pawn_attacks(bitboard, is_white, self.en_passent_square);
         if !only_attacks {
-          (moves, flags.passented_square, is_move_promotion) = pawn_moves(&bitboard, &occupancy, true);
+          (moves, flags.passented_square, is_move_promotion) = pawn_moves(bitboard, occupancy, is_white);
         
           if flags.can_be_en_passent {
-            attacks &= self.all_black_pieces() | self.en_passent_square.unwrap();
+            attacks &= enemy_pieces | self.en_passent_square.unwrap().bitboard();
           }
           else {
-            attacks &= self.all_black_pieces();
+            attacks &= enemy_pieces;
           }
         }
 
-        flags.is_promotion = is_move_promotion || is_attack_promotion;
-        moves |= attacks;
-         
-      },
-      PieceType::BlackPawn => {
-        let mut attacks;
-        let mut is_move_promotion = false;
-        let is_attack_promotion;
-
-        (attacks, flags.can_be_en_passent, is_attack_promotion) = pawn_attacks(&bitboard, false, self.en_passent_square);
-        if !only_attacks {
-          (moves, flags.passented_square, is_move_promotion) = pawn_moves(&bitboard, &occupancy, false);
-
-          if flags.can_be_en_passent {
-            attacks &= self.all_white_pieces() | self.en_passent_square.unwrap();
-          }
-          else {
-            attacks &= self.all_white_pieces();
-          }
-        }
-        
         flags.is_promotion = is_move_promotion || is_attack_promotion;
         moves |= attacks;
       }
     }
 
+    if !only_attacks {
+      moves &= !own_pieces;
+    }
+
     (moves, flags)
   }
-  fn get_all_legal_moves(&mut self) {
-    let mut all_pseudo_legal_moves: [Vec<Move>; 64] = [EMPTY_VEC; 64];
-    
-    for piece_type in PieceType::get_colour_types(self.white_to_move) {
-      for i in 0..64 {
-        let square_bitboard = 1 << i;
-
-        let bitboard = self.bitboards[piece_type as usize];
+  fn generate_legal_moves(&self, stage: GenerationStage, moves: &mut Vec<Move>) {
+    if !self.is_valid { // a missing king or an impossible check would send everything below off the rails
+      return;
+    }
 
-        if bitboard & square_bitboard != 0 {
-          let mut piece_moves = self.get_legal_moves(i, piece_type, false);
-          if square_bitboard & self.pinned_pieces != 0 {
-            piece_moves.0 &= self.pin_rays[i as usize];
-          }
-          all_pseudo_legal_moves[i as usize] = self.generate_moves_from_bitboard(i, piece_moves.0, piece_type, piece_moves.1);
-        }
+    // in double check the king has to move, in single check everything else has to capture or block the checker
+    let is_double_check = self.checks.len() > 1;
+    let mut check_mask = if self.checks.len() == 1 { self.checks[0] } else { Bitboard::ALL };
+    if let Some(square) = self.en_passent_square {
+      let passented_pawn = square.bitboard().shift(Direction::forward(!self.white_to_move));
+      if check_mask == passented_pawn { // the pawn that just double pushed is giving check, so taking it en passent gets out of check
+        check_mask |= square.bitboard();
       }
     }
 
-    let mut all_moves: [Vec<Move>; 64] = [EMPTY_VEC; 64];
-    for check in &self.checks {
-      for (i, piece_moves) in all_pseudo_legal_moves.iter().enumerate() {
-        let mut moves = Vec::new();
-        for piece_move in piece_moves {
-          if piece_move.moved_piece_type == PieceType::WhiteKing || piece_move.moved_piece_type == PieceType::BlackKing || check & 1 << piece_move.end_square != 0 {
-            moves.push(*piece_move);
-          }
+    let enemy_pieces = if self.white_to_move { self.all_black_pieces() } else { self.all_white_pieces() };
+    let empty_squares = !(self.all_white_pieces() | self.all_black_pieces());
+
+    for kind in PieceKind::ALL {
+      let piece = Piece::new(self.side_to_move(), kind);
+      let is_king = kind == PieceKind::King;
+      if is_double_check && !is_king {
+        continue;
+      }
+
+      for square in self.bitboards[piece.index()] {
+        let (mut targets, flags) = self.get_legal_moves(square, piece, false);
+        if self.pinned_pieces.contains(square) {
+          targets &= self.pin_rays[square.index()];
+        }
+        if !is_king {
+          targets &= check_mask;
         }
-        all_moves[i] = moves;
+
+        // the en passent square is empty, but a pawn that can take on it is making a capture
+        let en_passent_target = if flags.can_be_en_passent { self.en_passent_square.unwrap().bitboard() } else { Bitboard::EMPTY };
+        targets &= match stage {
+          GenerationStage::Captures => enemy_pieces | en_passent_target,
+          GenerationStage::Quiets => empty_squares & !en_passent_target,
+          GenerationStage::All => Bitboard::ALL,
+        };
+
+        self.generate_moves_from_bitboard(square, targets, flags, moves);
       }
     }
+    if stage == GenerationStage::Captures {
+      return;
+    }
 
-    if self.checks.len() == 0 {
-      let is_all_empty = all_moves.iter().all(|m| m.is_empty());
-      if is_all_empty {
-        self.moves = all_pseudo_legal_moves;
-        return;
+    // castles are added on their own, as the king can end up on a square it could walk to or on its own rook
+    let (can_castle_kingside, can_castle_queenside) = if self.white_to_move {
+      (self.castling_rights.white_kingside, self.castling_rights.white_queenside)
+    } else {
+      (self.castling_rights.black_kingside, self.castling_rights.black_queenside)
+    };
+    for (is_kingside, can_castle) in [(true, can_castle_kingside), (false, can_castle_queenside)] {
+      if !can_castle {
+        continue;
       }
+      let (king_start, rook_start, king_end, _) = self.castle_squares(self.white_to_move, is_kingside);
+      let end_square = if self.is_chess960 { rook_start } else { king_end };
+
+      let kind = if is_kingside { MoveKind::KingsideCastle } else { MoveKind::QueensideCastle };
+      moves.push(Move::new(king_start, end_square, kind));
     }
-    self.moves = all_moves;
   }
 
   pub fn make_move(&mut self, move_to_make: Move) {
-    self.castle_checks();
-    let new_piece_bitboard = 1 << move_to_make.end_square;
-    let old_piece_bitboard = 1 << move_to_make.start_square;
+    assert!(self.is_valid, "moves can't be made on an impossible position");
+    let (start_square, end_square, kind) = (move_to_make.get_start_square(), move_to_make.get_end_square(), move_to_make.get_kind());
+    let new_piece_bitboard = end_square.bitboard();
+    let old_piece_bitboard = start_square.bitboard();
+    let moved_piece = self.mailbox[start_square.index()].expect("there's no piece to move");
+    let captured_piece = if kind == MoveKind::EnPassant || !kind.is_capture() { None } else { self.mailbox[end_square.index()] };
+
+    let mut undo = UndoRecord {
+      move_made: move_to_make,
+      captured_piece: None,
+      castled_rook: None,
+      en_passent_square: self.en_passent_square,
+      white_castling_flags: self.white_castling_flags,
+      black_castling_flags: self.black_castling_flags,
+      halfmove_clock: self.halfmove_clock,
+      fullmove_num: self.fullmove_num,
+      hash: self.hash,
+    };
+    self.hash ^= self.castling_key() ^ en_passent_key(self.en_passent_square); // these get added back in once they're updated
     
-    if move_to_make.flags.is_promotion {
-      self.bitboards[move_to_make.moved_piece_type as usize] ^= old_piece_bitboard;
-      self.bitboards[move_to_make.promotion_piece.unwrap() as usize] |= new_piece_bitboard; 
+    if let Some(piece) = captured_piece {
+      self.toggle_pieces(piece, new_piece_bitboard);
+      undo.captured_piece = Some((piece, end_square));
     }
-    else {
-      self.bitboards[move_to_make.moved_piece_type as usize] ^= old_piece_bitboard | new_piece_bitboard;
+
+    if kind.is_castle() {
+      let (king_start, rook_start, king_end, rook_end) = self.castle_squares(self.white_to_move, kind == MoveKind::KingsideCastle);
+      let rook = Piece::new(moved_piece.color, PieceKind::Rook);
+      let rook_squares = rook_start.bitboard() ^ rook_end.bitboard();
+      self.toggle_pieces(moved_piece, king_start.bitboard() ^ king_end.bitboard()); // either of these can stay where they are in chess960
+      self.toggle_pieces(rook, rook_squares);
+      undo.castled_rook = Some((rook, rook_squares));
     }
-    
-    if let Some(piece_type) = move_to_make.captured_piece_type {
-      self.bitboards[piece_type as usize] ^= new_piece_bitboard;
+    else if let Some(promotion_kind) = move_to_make.get_promotion_kind() {
+      self.toggle_pieces(moved_piece, old_piece_bitboard);
+      self.toggle_pieces(Piece::new(moved_piece.color, promotion_kind), new_piece_bitboard);
+    }
+    else {
+      self.toggle_pieces(moved_piece, old_piece_bitboard | new_piece_bitboard);
     }
     
     // remove the passented piece
-    if move_to_make.flags.can_be_en_passent {
-      if self.en_passent_square.unwrap() & new_piece_bitboard != 0 {
-        if self.white_to_move {
-          self.bitboards[PieceType::BlackPawn as usize] ^= self.en_passent_square.unwrap() >> 8;
-        }
-        else {
-          self.bitboards[PieceType::WhitePawn as usize] ^= self.en_passent_square.unwrap() << 8;
-        }
-        self.en_passent_square = None;
-      }
+    if kind == MoveKind::EnPassant {
+      let passented_pawn = Piece::new(!moved_piece.color, PieceKind::Pawn);
+      let passented_square = end_square.offset(Direction::forward(!self.white_to_move)).unwrap();
+      self.toggle_pieces(passented_pawn, passented_square.bitboard());
+      undo.captured_piece = Some((passented_pawn, passented_square));
     }
 
-    if let Some(square) = move_to_make.flags.passented_square {
-      if new_piece_bitboard & square != 0 {
-        self.en_passent_square = if move_to_make.moved_piece_type == PieceType::WhitePawn {
-          Some(square >> 8)
-        }
-        else {
-          Some(square << 8)
-        };
-      }
-      else {
-        self.en_passent_square = None;
-      }
+    // a double push leaves the square it skipped over open to en passent
+    self.en_passent_square = if kind == MoveKind::DoublePush {
+      end_square.offset(Direction::forward(!self.white_to_move))
     }
     else {
-      self.en_passent_square = None;
+      None
+    };
+
+    // castling flags only ever get set, once a right is lost it's gone for good
+    match moved_piece.kind {
+      PieceKind::King => self.castling_flags_mut(moved_piece.color).king_moved = true,
+      PieceKind::Rook => self.rook_left_square(moved_piece.color, start_square),
+      _ => {}
+    }
+    if let Some(Piece { color, kind: PieceKind::Rook }) = captured_piece {
+      self.rook_left_square(color, end_square);
     }
 
-    // castling
-    if let Some(castle_square) = move_to_make.flags.kingside_castle_square {
-      if castle_square & new_piece_bitboard != 0 {
-        if self.white_to_move {
-          self.bitboards[PieceType::WhiteRook as usize] ^= 0x5;
-        }
-        else {
-          self.bitboards[PieceType::BlackRook as usize] ^= 0x500000000000000;
-        }
-      }
+    // clocks
+    let is_pawn_move = moved_piece.kind == PieceKind::Pawn;
+    if is_pawn_move || undo.captured_piece.is_some() {
+      self.halfmove_clock = 0;
     }
-    if let Some(castle_square) = move_to_make.flags.queenside_castle_square {
-      if castle_square & new_piece_bitboard != 0 {
-        if self.white_to_move {
-          self.bitboards[PieceType::WhiteRook as usize] ^= 0x90;
-        }
-        else {
-          self.bitboards[PieceType::BlackRook as usize] ^= 0x9000000000000000;
-        }
-      }
+    else {
+      self.halfmove_clock += 1;
+    }
+    if !self.white_to_move {
+      self.fullmove_num += 1;
     }
 
-    if !self.white_castling_flags.king_moved { // remove unneccecary checks
-      self.white_castling_flags.king_moved = PieceType::WhiteKing == move_to_make.moved_piece_type;
-      if PieceType::WhiteRook == move_to_make.moved_piece_type { // if the rook moves
-        self.white_castling_flags.rook_kingside_moved = old_piece_bitboard & H1 != 0;
-        self.white_castling_flags.rook_queenside_moved = old_piece_bitboard & A1 != 0;
-      }
-      else if move_to_make.captured_piece_type.is_some() && PieceType::WhiteRook == move_to_make.captured_piece_type.unwrap() { // if the rook is captured
-        self.white_castling_flags.rook_kingside_moved = new_piece_bitboard & H1 != 0;
-        self.white_castling_flags.rook_queenside_moved = new_piece_bitboard & A1 != 0;
-      }
+    self.undo_stack.push(undo);
+    self.white_to_move = !self.white_to_move;
+    self.hash ^= self.castling_key() ^ en_passent_key(self.en_passent_square) ^ side_key();
+    debug_assert_eq!(self.hash, self.calculate_hash(), "incremental hash drifted from the position");
+    debug_assert!(self.is_mailbox_in_sync(), "mailbox drifted from the bitboards");
+
+    self.update_position_state();
+  }
+  pub fn unmake_move(&mut self) { // takes back the last move made with make_move
+    if self.undo_stack.last().is_some_and(|undo| undo.move_made == Move::NULL) {
+      self.unmake_null_move();
+      return;
     }
-    if !self.black_castling_flags.king_moved { // remove unneccecary checks
-      self.black_castling_flags.king_moved = PieceType::BlackKing == move_to_make.moved_piece_type;
-      if PieceType::BlackRook == move_to_make.moved_piece_type { // if the rook moves
-        self.white_castling_flags.rook_kingside_moved = old_piece_bitboard & H8 != 0;
-        self.white_castling_flags.rook_queenside_moved = old_piece_bitboard & A8 != 0;
-      }
-      else if move_to_make.captured_piece_type.is_some() && PieceType::BlackRook == move_to_make.captured_piece_type.unwrap() { // if the rook is captured
-        self.white_castling_flags.rook_kingside_moved = new_piece_bitboard & H8 != 0;
-        self.white_castling_flags.rook_queenside_moved = new_piece_bitboard & A8 != 0;
-      }
+    let Some(undo) = self.undo_stack.pop() else {
+      return;
+    };
+    let move_to_unmake = undo.move_made;
+    let kind = move_to_unmake.get_kind();
+    let new_piece_bitboard = move_to_unmake.get_end_square().bitboard();
+    let old_piece_bitboard = move_to_unmake.get_start_square().bitboard();
+
+    self.white_to_move = !self.white_to_move;
+
+    if let Some((rook, rook_squares)) = undo.castled_rook {
+      self.toggle_bitboard(rook, rook_squares);
     }
 
-    self.detect_check();
+    if kind.is_castle() {
+      let (king_start, _, king_end, _) = self.castle_squares(self.white_to_move, kind == MoveKind::KingsideCastle);
+      self.toggle_bitboard(Piece::new(self.side_to_move(), PieceKind::King), king_start.bitboard() ^ king_end.bitboard());
+    }
+    else if let Some(promotion_kind) = move_to_unmake.get_promotion_kind() {
+      self.toggle_bitboard(Piece::new(self.side_to_move(), promotion_kind), new_piece_bitboard);
+      self.toggle_bitboard(Piece::new(self.side_to_move(), PieceKind::Pawn), old_piece_bitboard);
+    }
+    else {
+      let moved_piece = self.mailbox[move_to_unmake.get_end_square().index()].unwrap();
+      self.toggle_bitboard(moved_piece, old_piece_bitboard | new_piece_bitboard);
+    }
+
+    if let Some((piece, square)) = undo.captured_piece {
+      self.toggle_bitboard(piece, square.bitboard());
+    }
+
+    self.en_passent_square = undo.en_passent_square;
+    self.white_castling_flags = undo.white_castling_flags;
+    self.black_castling_flags = undo.black_castling_flags;
+    self.halfmove_clock = undo.halfmove_clock;
+    self.fullmove_num = undo.fullmove_num;
+    self.hash = undo.hash;
+    debug_assert!(self.is_mailbox_in_sync(), "mailbox drifted from the bitboards");
+
+    self.update_position_state();
+  }
+
+  // passes the turn, for null move pruning and seeing what the opponent would do with a free move.
+  // passing while in check would let the king be taken, so it doesn't make the move and returns false then (or if the position is impossible)
+  pub fn make_null_move(&mut self) -> bool {
+    if self.is_in_check() || !self.is_valid {
+      return false;
+    }
+
+    self.undo_stack.push(UndoRecord {
+      move_made: Move::NULL,
+      captured_piece: None,
+      castled_rook: None,
+      en_passent_square: self.en_passent_square,
+      white_castling_flags: self.white_castling_flags,
+      black_castling_flags: self.black_castling_flags,
+      halfmove_clock: self.halfmove_clock,
+      fullmove_num: self.fullmove_num,
+      hash: self.hash,
+    });
+    self.hash ^= en_passent_key(self.en_passent_square) ^ side_key();
+    self.en_passent_square = None;
+    self.halfmove_clock = 0; // positions from before a pass never really happened, so repetitions shouldn't look past it
     self.white_to_move = !self.white_to_move;
-    self.find_pinned_pieces();
-    self.get_opponents_attacks();
-    self.get_all_legal_moves();
+    debug_assert_eq!(self.hash, self.calculate_hash(), "incremental hash drifted from the position");
+
+    self.update_position_state();
+    true
+  }
+  pub fn unmake_null_move(&mut self) {
+    let Some(undo) = self.undo_stack.pop_if(|undo| undo.move_made == Move::NULL) else {
+      return;
+    };
+
+    self.white_to_move = !self.white_to_move;
+    self.en_passent_square = undo.en_passent_square;
+    self.halfmove_clock = undo.halfmove_clock;
+    self.hash = undo.hash;
+
+    self.update_position_state();
   }
 
   pub fn is_checkmate(&self) -> bool {
-    self.get_all_moves().len() == 0 && self.checks.len() != 0
+    self.is_valid && self.get_all_moves().is_empty() && self.is_in_check()
+  }
+  pub fn is_threefold_repetition(&self) -> bool {
+    // the undo stack holds the key of every position before each move. only positions since the last capture or pawn move can repeat,
+    // and only every second one has the same side to move
+    let mut repetitions = 1;
+    for undo in self.undo_stack.iter().rev().take(self.halfmove_clock as usize).skip(1).step_by(2) {
+      if undo.hash == self.hash {
+        repetitions += 1;
+        if repetitions >= 3 {
+          return true;
+        }
+      }
+    }
+    false
+  }
+  pub fn is_fifty_move_draw(&self) -> bool {
+    self.halfmove_clock >= 100
+  }
+  pub fn is_insufficient_material(&self) -> bool { // neither side can ever checkmate, no matter how badly the other plays
+    if [PieceKind::Queen, PieceKind::Rook, PieceKind::Pawn].iter().any(|kind| !self.pieces_of_kind(*kind).is_empty()) {
+      return false;
+    }
+
+    let knights = self.pieces_of_kind(PieceKind::Knight);
+    let bishops = self.pieces_of_kind(PieceKind::Bishop);
+    if (knights | bishops).count() <= 1 { // a lone minor piece cant mate
+      return true;
+    }
+
+    // any number of bishops that are all on the same colour can't mate either
+    knights.is_empty() && ((bishops & Bitboard::LIGHT_SQUARES).is_empty() || (bishops & !Bitboard::LIGHT_SQUARES).is_empty())
+  }
+  pub fn status(&self) -> GameStatus {
+    if !self.is_valid { // there are no moves, but that doesn't make it mate or stalemate
+      return GameStatus::ImpossiblePosition;
+    }
+    if self.get_all_moves().is_empty() {
+      return if self.checks.is_empty() { GameStatus::Stalemate } else { GameStatus::Checkmate(!self.side_to_move()) };
+    }
+    if self.is_insufficient_material() {
+      GameStatus::InsufficientMaterial
+    }
+    else if self.is_threefold_repetition() {
+      GameStatus::Repetition
+    }
+    else if self.is_fifty_move_draw() {
+      GameStatus::FiftyMove
+    }
+    else {
+      GameStatus::Ongoing
+    }
   }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn null_moves() {
+    let mut board = Board::new("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3");
+    let before = board.to_fen();
+
+    assert!(board.make_null_move());
+    assert_eq!(board.get_hash(), Board::new("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3").get_hash());
+    assert!(board.get_if_white_to_move());
+    assert_eq!(board.get_all_moves().len(), 29);
+
+    // a real move can still be made and taken back on top of a pass
+    board.make_move(board.parse_uci_move("f1b5").unwrap());
+    assert!(board.is_in_check());
+    assert!(!board.make_null_move());
+    board.unmake_move();
+
+    board.unmake_move(); // takes back the pass as well
+    assert_eq!(board.to_fen(), before);
+    assert_eq!(board.get_hash(), Board::new(&before).get_hash());
+  }
+
+  fn play(board: &mut Board, sans: &[&str]) {
+    for san in sans {
+      board.make_move(board.parse_san(san).unwrap());
+    }
+  }
+
+  #[test]
+  fn repetition_and_fifty_moves() {
+    let mut board = Board::new(STARTING_FEN);
+    play(&mut board, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"]);
+    assert!(!board.is_threefold_repetition());
+    play(&mut board, &["Ng8"]); // the starting position for the third time
+    assert!(board.is_threefold_repetition());
+    assert_eq!(board.status(), GameStatus::Repetition);
+
+    let mut board = Board::new("4k3/8/8/8/8/8/4P3/4K2R w K - 99 80");
+    assert!(!board.is_fifty_move_draw());
+    play(&mut board, &["Rh2"]);
+    assert!(board.is_fifty_move_draw());
+    assert_eq!(board.status(), GameStatus::FiftyMove);
+    play(&mut board, &["Kd7", "e4"]);
+    assert_eq!(board.halfmove_clock, 0);
+    assert!(!board.is_fifty_move_draw());
+  }
+
+  #[test]
+  fn statuses() {
+    let status = |fen| Board::new(fen).status();
+    assert_eq!(status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameStatus::Stalemate);
+    assert_eq!(status("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), GameStatus::InsufficientMaterial);
+    assert_eq!(status("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"), GameStatus::InsufficientMaterial);
+    assert_eq!(status("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"), GameStatus::InsufficientMaterial); // both bishops are on dark squares
+    assert_eq!(status("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"), GameStatus::Ongoing); // opposite colours can still mate
+    assert_eq!(status("4k3/8/8/8/8/8/8/1N2K1N1 w - - 0 1"), GameStatus::Ongoing); // two knights can't force it, but a mate is possible
+    assert_eq!(status("r1bqkbnr/pppp1Qpp/2n5/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4"), GameStatus::Checkmate(Color::White));
+
+    // mate and stalemate come before any other draw
+    assert_eq!(status("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 90"), GameStatus::Checkmate(Color::White));
+    assert_eq!(status("7k/5Q2/6K1/8/8/8/8/8 b - - 120 90"), GameStatus::Stalemate);
+    assert!(!GameStatus::Checkmate(Color::Black).is_draw());
+    assert!(GameStatus::FiftyMove.is_draw());
+  }
+}
@@ -1,190 +1,81 @@
 use crate::board_representation::precompiled_bitboards::*;
+use crate::board_representation::bitboard::{Bitboard, Square, Direction};
 
-// from white's perspective
-pub const TOP_RANK: u64 = 0xFF00000000000000;
-pub const BOTTOM_RANK: u64 = 0x00000000000000FF;
-pub const LEFT_FILE: u64 = 0x8080808080808080;
-pub const RIGHT_FILE: u64 = 0x0101010101010101;
-
-pub const RANK_SHIFT: i32 = 8; // value to shift if you want to move ranks
-pub const FILE_SHIFT: i32 = 1; // value to shift if you want to move files
-
-pub fn pawn_attacks(bitboard: &u64, is_white: bool, en_passent_square: Option<u64>) -> (u64, bool, bool) {
-  let mut attacks: u64 = 0;
+pub fn pawn_attack_squares(pawn: Bitboard, is_white: bool) -> Bitboard {
+  let forward = pawn.shift(Direction::forward(is_white));
+  forward.shift(Direction::East) | forward.shift(Direction::West)
+}
+pub fn pawn_attacks(pawn: Bitboard, is_white: bool, en_passent_square: Option<Square>) -> (Bitboard, bool, bool) {
+  let attacks = pawn_attack_squares(pawn, is_white);
 
   // flags
-  let mut can_be_en_passent = false; // square that the passenting piece ends up on
-  let mut is_promotion = false;
-
-  if is_white {
-    if bitboard & RIGHT_FILE == 0 { // if piece is not on the left file
-      attacks |= bitboard << (RANK_SHIFT - 1)
-    }
-    if bitboard & LEFT_FILE == 0 { // if piece is not on the right file
-      attacks |= bitboard << (RANK_SHIFT + 1);
-    }
-
-    if attacks & TOP_RANK != 0 {
-      is_promotion = true;
-    }
-  } else {
-    if bitboard & RIGHT_FILE == 0 { // if piece is not on the left file
-      attacks |= bitboard >> (RANK_SHIFT + 1)
-    }
-    if bitboard & LEFT_FILE == 0 { // if piece is not on the right file
-      attacks |= bitboard >> (RANK_SHIFT - 1);
-    }
-
-    if attacks & BOTTOM_RANK != 0 {
-      is_promotion = true;
-    }
-  }
-
+  let can_be_en_passent = en_passent_square.is_some_and(|square| attacks.contains(square)); // allows for the capture of en_passent
+  let is_promotion = !(attacks & (Bitboard::RANK_1 | Bitboard::RANK_8)).is_empty();
 
-  if let Some(square) = en_passent_square { // allows for the capture of en_passent
-    let en_passent_attack = attacks & square;
-    if en_passent_attack != 0 {
-      attacks |= en_passent_attack;
-      can_be_en_passent = true;
-    }
-  }
-  
   (attacks, can_be_en_passent, is_promotion)
 }
-pub fn pawn_moves(bitboard: &u64, occupancy: &u64, is_white: bool) -> (u64, Option<u64>, bool) {
-  let mut moves: u64 = 0;
-
-  let mut can_be_passented_square = None; // square that pawns can be passented  on (https://www.youtube.com/shorts/wOdObmJ-q9A)
-  let mut is_promotion = false;
-
-  if is_white {
-    let pawn_move = bitboard << RANK_SHIFT;
-    moves |= pawn_move;
+pub fn pawn_moves(pawn: Bitboard, occupancy: Bitboard, is_white: bool) -> (Bitboard, Option<Square>, bool) {
+  let forward = Direction::forward(is_white);
+  let starting_rank = if is_white { Bitboard::rank(1) } else { Bitboard::rank(6) };
 
-    if pawn_move & TOP_RANK != 0 {
-      is_promotion = true;
-    }
+  let pawn_move = pawn.shift(forward) & !occupancy;
+  let mut moves = pawn_move;
+  let is_promotion = !(pawn_move & (Bitboard::RANK_1 | Bitboard::RANK_8)).is_empty();
 
-    if bitboard & (BOTTOM_RANK << RANK_SHIFT) != 0 { // if pawn is on 2nd rank
-      let move_square  = bitboard << (RANK_SHIFT * 2);
-      moves |= move_square;
-      can_be_passented_square = Some(move_square);
-    }
-  } else {
-    let pawn_move = bitboard >> RANK_SHIFT;
-    moves |= pawn_move;
-
-    if pawn_move & BOTTOM_RANK != 0 {
-      is_promotion = true
-    }
-    if bitboard & (TOP_RANK >> RANK_SHIFT) != 0 { // if pawn is on 7th rank
-      let move_square  = bitboard >> (RANK_SHIFT * 2);
-      moves |= move_square;
-      can_be_passented_square = Some(move_square);
-    }
+  let mut can_be_passented_square = None; // square that pawns can be passented  on (https://www.youtube.com/shorts/wOdObmJ-q9A)
+  if !(pawn & starting_rank).is_empty() { // if nothing is in front of it, the empty shift stops the double push
+    let double_push = pawn_move.shift(forward) & !occupancy;
+    moves |= double_push;
+    can_be_passented_square = double_push.first_square();
   }
 
-  moves ^= occupancy & moves;
-
   (moves, can_be_passented_square, is_promotion)
 }
 
-pub fn knight_moves(bitboard: &u64) -> u64 {
-  let mut moves = 0;
-
-  if (bitboard & TOP_RANK == 0) && (bitboard & (LEFT_FILE | (LEFT_FILE >> FILE_SHIFT)) == 0) { // if not on top rank AND if not on the two left-most files\
-    moves |= bitboard << 10; // up left left
-  }
-  if (bitboard & (TOP_RANK & (TOP_RANK >> RANK_SHIFT)) == 0) && (bitboard & LEFT_FILE == 0) { // if not on the two top-most ranks AND if not on the left file
-    moves |= bitboard << 17; // up up left
-  }
-  if (bitboard & (TOP_RANK & (TOP_RANK >> RANK_SHIFT)) == 0) && (bitboard & RIGHT_FILE == 0) { // if not on the two top-most ranks AND if not on the right file
-    moves |= bitboard << 15; // up up right
-  }
-  if (bitboard & TOP_RANK == 0) && (bitboard & (RIGHT_FILE | (RIGHT_FILE << FILE_SHIFT)) == 0) { // if not on top rank AND if not on the two right-most files
-    moves |= bitboard << 6; // up right right
-  }
-  if (bitboard & BOTTOM_RANK == 0) && (bitboard & (RIGHT_FILE | (RIGHT_FILE << FILE_SHIFT)) == 0) { // if not on bottom rank AND if not on the two right-most files
-    moves |= bitboard >> 10; // down right right
-  }
-  if (bitboard & (BOTTOM_RANK & (BOTTOM_RANK << RANK_SHIFT)) == 0) && (bitboard & RIGHT_FILE == 0) { // if not on the two bottom-most ranks AND if not on the right file
-    moves |= bitboard >> 17; // down down right
-  }
-  if (bitboard & (BOTTOM_RANK & (BOTTOM_RANK << RANK_SHIFT)) == 0) && (bitboard & LEFT_FILE == 0) { // if not on the two bottom-most ranks AND if not on the left file
-    moves |= bitboard >> 15; // down down left
-  }
-  if (bitboard & BOTTOM_RANK == 0) && (bitboard & (LEFT_FILE | (LEFT_FILE >> FILE_SHIFT)) == 0) { // if not on bottom rank AND if not on the two left-most files
-    moves |= bitboard >> 6; // down left left
-  }
-
-  moves
+pub fn knight_moves(knight: Bitboard) -> Bitboard {
+  // one square straight and one diagonally outwards from it, which cant wrap around the board as each shift stops at the edge
+  let north = knight.shift(Direction::North);
+  let south = knight.shift(Direction::South);
+  let east = knight.shift(Direction::East);
+  let west = knight.shift(Direction::West);
+
+  north.shift(Direction::NorthEast) | north.shift(Direction::NorthWest) |
+  south.shift(Direction::SouthEast) | south.shift(Direction::SouthWest) |
+  east.shift(Direction::NorthEast) | east.shift(Direction::SouthEast) |
+  west.shift(Direction::NorthWest) | west.shift(Direction::SouthWest)
+}
+pub fn king_moves(king: Bitboard) -> Bitboard { // castling is handled by the board, as it needs to know where the rooks are
+  Direction::ALL.iter().fold(Bitboard::EMPTY, |moves, direction| moves | king.shift(*direction))
 }
-pub fn king_moves(bitboard: &u64, castle_kingside:bool, castle_queenside: bool) -> (u64, Option<u64>, Option<u64>) {
-  let mut moves = 0;
-  let mut is_castles_kingside = None;
-  let mut is_castles_queenside = None;
-
-  if bitboard & TOP_RANK == 0 { // if not on the top of the board
-    moves |= bitboard << RANK_SHIFT; // up
-    
-    if bitboard & RIGHT_FILE == 0 { // if not on the right of the board
-      moves |= bitboard << RANK_SHIFT - 1; // up right
-    }
-    if bitboard & LEFT_FILE == 0 { // if not on the left of the board
-      moves |= bitboard << RANK_SHIFT + 1; // up left
-    }
-  }
-  if bitboard & BOTTOM_RANK == 0 { // if not on the bottom of the board
-    moves |= bitboard >> RANK_SHIFT; // down
 
-    if bitboard & LEFT_FILE == 0 { // if not on the left of the board
-      moves |= bitboard >> RANK_SHIFT - 1; // down left
-    }
-    if bitboard & RIGHT_FILE == 0 { // if not on the right of the board
-      moves |= bitboard >> RANK_SHIFT + 1; // down right
-    }
+pub fn squares_between(square_a: Square, square_b: Square) -> Bitboard {
+  if get_rook_moves(square_a, Bitboard::EMPTY).contains(square_b) {
+    get_rook_moves(square_a, square_b.bitboard()) & get_rook_moves(square_b, square_a.bitboard())
   }
-  if bitboard & LEFT_FILE == 0 { // if not on the left of the board
-    moves |= bitboard << FILE_SHIFT; // left
+  else if get_bishop_moves(square_a, Bitboard::EMPTY).contains(square_b) {
+    get_bishop_moves(square_a, square_b.bitboard()) & get_bishop_moves(square_b, square_a.bitboard())
   }
-  if bitboard & RIGHT_FILE == 0 { // if not on the right of the board
-    moves |= bitboard >> FILE_SHIFT; // right
+  else {
+    Bitboard::EMPTY
   }
-
-  if castle_kingside {
-    let castle = bitboard >> 2;
-    moves |= castle;
-    is_castles_kingside = Some(castle);
-  }
-  if castle_queenside {
-    let castle = bitboard << 2;
-    moves |= castle;
-    is_castles_queenside = Some(castle)
-  }
-
-  (moves, is_castles_kingside, is_castles_queenside)
 }
 
-pub fn get_magic_index(magic: u64, index_bits: u32, mask: u64, population: &u64) -> usize {
-  let blockers = population & mask;
+fn get_magic_index(magic: u64, index_bits: u32, mask: u64, population: Bitboard) -> usize {
+  let blockers = population.0 & mask;
 
   (blockers.wrapping_mul(magic) >> index_bits) as usize
 }
-pub fn get_bishop_moves(square_index: i32, population: &u64) -> u64 {
-  let magic = &BISHOP_MAGICS[square_index as usize];
-  let mask = &BISHOP_MASKS[square_index as usize];
-  let relevant_bits = &BISHOP_BITS[square_index as usize];
+pub fn get_bishop_moves(square: Square, population: Bitboard) -> Bitboard {
+  let magic = BISHOP_MAGICS[square.index()];
+  let mask = BISHOP_MASKS[square.index()];
+  let relevant_bits = BISHOP_BITS[square.index()];
 
-  let moves = BISHOP_MOVES[square_index as usize][get_magic_index(*magic, *relevant_bits, *mask, &population)];
-  
-  moves
+  Bitboard(BISHOP_MOVES[square.index()][get_magic_index(magic, relevant_bits, mask, population)])
 }
-pub fn get_rook_moves(square_index: i32, population: &u64) -> u64 { // FIXME: for some reason the rook cant reach the edge of the board in some cases
-  let magic = &ROOK_MAGICS[square_index as usize];
-  let mask = &ROOK_MASKS[square_index as usize];
-  let relevant_bits = &ROOK_BITS[square_index as usize];
+pub fn get_rook_moves(square: Square, population: Bitboard) -> Bitboard {
+  let magic = ROOK_MAGICS[square.index()];
+  let mask = ROOK_MASKS[square.index()];
+  let relevant_bits = ROOK_BITS[square.index()];
 
-  let moves = ROOK_MOVES[square_index as usize][get_magic_index(*magic, *relevant_bits, *mask, &population)];
-  
-  moves
+  Bitboard(ROOK_MOVES[square.index()][get_magic_index(magic, relevant_bits, mask, population)])
 }
\ No newline at end of file
@@ -0,0 +1,303 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::board_representation::{Board, Move, MoveKind, GameStatus, Square};
+use crate::utils::PieceKind;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NotationError {
+  Malformed(String), // the text isn't a move at all
+  IllegalMove(String), // it's a move, just not one that can be played here
+  AmbiguousMove(String), // more than one legal move fits
+}
+impl fmt::Display for NotationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      NotationError::Malformed(text) => write!(f, "'{}' is not a valid move", text),
+      NotationError::IllegalMove(text) => write!(f, "'{}' is not a legal move in this position", text),
+      NotationError::AmbiguousMove(text) => write!(f, "'{}' could be more than one move", text),
+    }
+  }
+}
+impl std::error::Error for NotationError {}
+
+/* UCI */
+impl Move {
+  // long algebraic notation like e2e4 or e7e8q, the way UCI engines and GUIs write moves
+  pub fn to_uci(self) -> String {
+    UciMove::from(self).to_string()
+  }
+}
+
+fn promotion_from_char(c: char) -> Option<PieceKind> { // either case
+  PieceKind::from_char(c).filter(|kind| !matches!(kind, PieceKind::King | PieceKind::Pawn))
+}
+
+// a move in UCI that hasn't been checked against a board yet, so it can be read without one and resolved later
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UciMove {
+  pub start_square: Square,
+  pub end_square: Square,
+  pub promotion_kind: Option<PieceKind>,
+}
+impl From<Move> for UciMove {
+  fn from(piece_move: Move) -> Self {
+    Self {
+      start_square: piece_move.get_start_square(),
+      end_square: piece_move.get_end_square(),
+      promotion_kind: piece_move.get_promotion_kind(),
+    }
+  }
+}
+impl FromStr for UciMove {
+  type Err = NotationError;
+
+  fn from_str(uci: &str) -> Result<Self, NotationError> {
+    let malformed = || NotationError::Malformed(uci.to_string());
+    if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+      return Err(malformed());
+    }
+
+    let start_square = Square::from_algebraic(&uci[0..2]).ok_or_else(malformed)?;
+    let end_square = Square::from_algebraic(&uci[2..4]).ok_or_else(malformed)?;
+    let promotion_kind = match uci[4..].chars().next() {
+      Some(c) => Some(promotion_from_char(c).ok_or_else(malformed)?),
+      None => None
+    };
+    Ok(Self { start_square, end_square, promotion_kind })
+  }
+}
+impl fmt::Display for UciMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", self.start_square, self.end_square)?;
+    if let Some(kind) = self.promotion_kind { // uci promotions are always lowercase
+      write!(f, "{}", kind.to_char())?;
+    }
+    Ok(())
+  }
+}
+
+impl Board {
+  pub fn parse_uci_move(&self, uci: &str) -> Result<Move, NotationError> {
+    self.resolve_uci_move(uci.parse()?)
+  }
+  pub fn resolve_uci_move(&self, uci_move: UciMove) -> Result<Move, NotationError> {
+    // the legal move has all the flags and the captured piece filled in already
+    self.get_moves(uci_move.start_square).into_iter()
+      .find(|m| m.get_end_square() == uci_move.end_square && m.get_promotion_kind() == uci_move.promotion_kind)
+      .ok_or_else(|| NotationError::IllegalMove(uci_move.to_string()))
+  }
+}
+
+/* SAN */
+fn piece_letter(kind: PieceKind) -> Option<char> { // pawns dont get a letter
+  match kind {
+    PieceKind::Pawn => None,
+    _ => Some(kind.to_char().to_ascii_uppercase())
+  }
+}
+
+impl Board {
+  // standard algebraic notation like Nbd7, exd6, O-O-O or e8=Q#
+  pub fn move_to_san(&self, piece_move: Move) -> String {
+    let mut san = String::new();
+
+    if piece_move.is_castle() {
+      san.push_str(if piece_move.get_kind() == MoveKind::KingsideCastle { "O-O" } else { "O-O-O" });
+    }
+    else {
+      let (start_square, end_square) = (piece_move.get_start_square(), piece_move.get_end_square());
+      let start_name = start_square.to_string();
+      let moved_piece = self.piece_at(start_square).unwrap();
+      match piece_letter(moved_piece.kind) {
+        Some(letter) => {
+          san.push(letter);
+
+          // other pieces of the same type that could go to the same square
+          let rivals: Vec<Move> = self.get_all_moves().into_iter()
+            .filter(|m| m.get_end_square() == end_square && m.get_start_square() != start_square && self.piece_at(m.get_start_square()) == Some(moved_piece))
+            .collect();
+          if !rivals.is_empty() {
+            let shares_file = rivals.iter().any(|m| m.get_start_square().file() == start_square.file());
+            let shares_rank = rivals.iter().any(|m| m.get_start_square().rank() == start_square.rank());
+            if !shares_file {
+              san.push_str(&start_name[0..1]);
+            }
+            else if !shares_rank {
+              san.push_str(&start_name[1..2]);
+            }
+            else {
+              san.push_str(&start_name);
+            }
+          }
+        },
+        None => {
+          if piece_move.is_capture() { // pawn captures always say which file they came from
+            san.push_str(&start_name[0..1]);
+          }
+        }
+      }
+
+      if piece_move.is_capture() {
+        san.push('x');
+      }
+      san.push_str(&end_square.to_string());
+
+      if let Some(kind) = piece_move.get_promotion_kind() {
+        san.push('=');
+        san.push(kind.to_char().to_ascii_uppercase());
+      }
+    }
+
+    let mut board = self.clone();
+    board.make_move(piece_move);
+    if let GameStatus::Checkmate(_) = board.status() {
+      san.push('#');
+    }
+    else if board.is_in_check() {
+      san.push('+');
+    }
+
+    san
+  }
+
+  // takes SAN the way people actually write it, so check marks, annotations, "e.p.", 0-0 and missing = signs are all fine
+  pub fn parse_san(&self, san: &str) -> Result<Move, NotationError> {
+    let malformed = || NotationError::Malformed(san.to_string());
+
+    // the check and annotation marks can come before or after the e.p.
+    let mut text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    for suffix in ["e.p.", "ep"] {
+      if let Some(stripped) = text.strip_suffix(suffix) {
+        text = stripped.trim_end();
+      }
+    }
+    let text = text.trim_end_matches(['+', '#', '!', '?']);
+    if text.is_empty() || !text.is_ascii() {
+      return Err(malformed());
+    }
+
+    let legal_moves = self.get_all_moves();
+    let find_single = |candidates: Vec<Move>| match candidates.len() {
+      0 => Err(NotationError::IllegalMove(san.to_string())),
+      1 => Ok(candidates[0]),
+      _ => Err(NotationError::AmbiguousMove(san.to_string())),
+    };
+
+    // castling
+    let castle = text.replace('0', "O");
+    if castle == "O-O" || castle == "O-O-O" {
+      let kingside = castle == "O-O";
+      return find_single(legal_moves.into_iter().filter(|m| m.get_kind() == if kingside { MoveKind::KingsideCastle } else { MoveKind::QueensideCastle }).collect());
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+
+    // piece letter, no letter means a pawn
+    let kind = match chars[0] {
+      'K' | 'Q' | 'B' | 'N' | 'R' => PieceKind::from_char(chars.remove(0)).unwrap(),
+      _ => PieceKind::Pawn,
+    };
+
+    // promotion, with or without the =
+    let mut promotion_kind = None;
+    if chars.len() > 2 && kind == PieceKind::Pawn && !chars[chars.len() - 1].is_ascii_digit() {
+      promotion_kind = Some(promotion_from_char(chars.pop().unwrap()).ok_or_else(malformed)?);
+      if chars.last() == Some(&'=') {
+        chars.pop();
+      }
+    }
+
+    // whatever is left is an optional from file/rank, capture marks and the destination
+    chars.retain(|c| !matches!(c, 'x' | ':' | '-'));
+    if chars.len() < 2 || chars.len() > 4 {
+      return Err(malformed());
+    }
+    let destination: String = chars[chars.len() - 2..].iter().collect();
+    let end_square = Square::from_algebraic(&destination).ok_or_else(malformed)?;
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in &chars[..chars.len() - 2] {
+      match c {
+        'a'..='h' => from_file = Some(*c as u8 - b'a'),
+        '1'..='8' => from_rank = Some(*c as u8 - b'1'),
+        _ => return Err(malformed()),
+      }
+    }
+
+    find_single(legal_moves.into_iter().filter(|m| {
+      self.piece_at(m.get_start_square()).is_some_and(|piece| piece.kind == kind)
+        && m.get_end_square() == end_square
+        && m.get_promotion_kind() == promotion_kind
+        && from_file.is_none_or(|file| m.get_start_square().file() == file)
+        && from_rank.is_none_or(|rank| m.get_start_square().rank() == rank)
+    }).collect())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board_representation::STARTING_FEN;
+
+  #[test]
+  fn uci() {
+    let board = Board::new("k7/4P3/8/8/8/8/8/4K2R w K - 0 1");
+    let promotion = board.parse_uci_move("e7e8q").unwrap();
+    assert_eq!(promotion.get_promotion_kind(), Some(PieceKind::Queen));
+    assert_eq!(promotion.to_uci(), "e7e8q");
+    assert_eq!(board.parse_uci_move("e7e8N").unwrap().to_uci(), "e7e8n");
+    assert_eq!(board.parse_uci_move("e1g1").unwrap().get_kind(), MoveKind::KingsideCastle);
+    assert_eq!(Board::new(STARTING_FEN).parse_uci_move("e2e4").unwrap().get_kind(), MoveKind::DoublePush);
+
+    for malformed in ["", "e7e", "e7e8qq", "i7i8", "e7e8k", "e7e8 ", "e7-e8"] {
+      assert_eq!(board.parse_uci_move(malformed), Err(NotationError::Malformed(malformed.to_string())));
+    }
+    assert_eq!(board.parse_uci_move("e7e8"), Err(NotationError::IllegalMove("e7e8".to_string()))); // it has to say what it promotes to
+    assert_eq!(board.parse_uci_move("e1e3"), Err(NotationError::IllegalMove("e1e3".to_string())));
+  }
+
+  #[test]
+  fn san() {
+    let cases = [
+      ("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "b8d7", "Nbd7"),
+      ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3"),
+      ("4k3/8/8/8/8/1N3N2/8/1N5K w - - 0 1", "b3d2", "Nb3d2"),
+      ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8", "Ra8+"),
+      ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#"),
+      ("k7/4P3/1K6/8/8/8/8/8 w - - 0 1", "e7e8q", "e8=Q#"),
+      ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1c1", "O-O-O"),
+      ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8g8", "O-O"),
+      ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+      ("8/2k5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6+"),
+    ];
+    for (fen, uci, san) in cases {
+      let board = Board::new(fen);
+      let piece_move = board.parse_uci_move(uci).unwrap();
+      assert_eq!(board.move_to_san(piece_move), san);
+      assert_eq!(board.parse_san(san), Ok(piece_move));
+    }
+
+    // the looser ways people write the same moves
+    let loose = [
+      ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "0-0-0", "e1c1"),
+      ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "0-0", "e1g1"),
+      ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6 e.p.", "e5d6"),
+      ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6ep", "e5d6"),
+      ("8/2k5/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6e.p.+", "e5d6"),
+      ("8/2k5/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6+ e.p.", "e5d6"),
+      ("k7/4P3/1K6/8/8/8/8/8 w - - 0 1", "e8Q", "e7e8q"),
+      ("4k3/8/8/8/8/1N3N2/8/1N5K w - - 0 1", "Nb3-d2!?", "b3d2"),
+    ];
+    for (fen, san, uci) in loose {
+      let board = Board::new(fen);
+      assert_eq!(board.parse_san(san), Ok(board.parse_uci_move(uci).unwrap()), "{}", san);
+    }
+    assert_eq!(Board::new("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").parse_san("exd6 e.p.").unwrap().get_kind(), MoveKind::EnPassant);
+
+    let board = Board::new("4k3/8/8/8/8/1N3N2/8/1N5K w - - 0 1");
+    assert_eq!(board.parse_san("Nd2"), Err(NotationError::AmbiguousMove("Nd2".to_string())));
+    assert_eq!(board.parse_san("Nbd2"), Err(NotationError::AmbiguousMove("Nbd2".to_string())));
+    assert_eq!(board.parse_san("Ne4"), Err(NotationError::IllegalMove("Ne4".to_string())));
+    assert_eq!(board.parse_san("Nz9"), Err(NotationError::Malformed("Nz9".to_string())));
+  }
+}
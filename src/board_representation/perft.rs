@@ -0,0 +1,166 @@
+use crate::board_representation::{Board, Move};
+
+// counts every leaf of the move tree, the numbers can be checked against known results to find move gen bugs
+impl Board {
+  pub fn perft(&mut self, depth: u32) -> u64 {
+    if depth == 0 {
+      return 1;
+    }
+
+    let moves = self.get_all_moves();
+    if depth == 1 {
+      return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for piece_move in moves {
+      self.make_move(piece_move);
+      nodes += self.perft(depth - 1);
+      self.unmake_move();
+    }
+    nodes
+  }
+
+  // perft split up by the first move, so a wrong total can be narrowed down to the move that causes it
+  pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+    let mut results = Vec::new();
+    if depth == 0 {
+      return results;
+    }
+
+    for piece_move in self.get_all_moves() {
+      self.make_move(piece_move);
+      results.push((piece_move, self.perft(depth - 1)));
+      self.unmake_move();
+    }
+    results
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // positions and node counts from https://www.chessprogramming.org/Perft_Results
+  const START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+  const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+  const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+  const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+  const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+  const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+  const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+  fn assert_perft(fen: &str, expected: &[u64]) {
+    let mut board = Board::new(fen);
+    for (depth, nodes) in expected.iter().enumerate() {
+      assert_eq!(board.perft(depth as u32 + 1), *nodes, "perft({}) of {}", depth + 1, fen);
+    }
+    assert_eq!(board.to_fen(), fen, "perft should leave the board how it found it");
+  }
+
+  fn assert_perft_at(fen: &str, depth: u32, expected: u64) {
+    let mut board = Board::new(fen);
+    assert_eq!(board.perft(depth), expected, "perft({}) of {}", depth, fen);
+  }
+
+  #[test]
+  fn start_position() {
+    assert_perft(START_POSITION, &[20, 400, 8902, 197281]);
+  }
+  #[test]
+  fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039, 97862]);
+  }
+  #[test]
+  fn en_passent_and_rank_pins() {
+    assert_perft(POSITION_3, &[14, 191, 2812, 43238, 674624]);
+  }
+  #[test]
+  fn promotions_and_castling() {
+    assert_perft(POSITION_4, &[6, 264, 9467, 422333]);
+    assert_perft(POSITION_4_MIRRORED, &[6, 264, 9467, 422333]);
+  }
+  #[test]
+  fn promotion_with_check() {
+    assert_perft(POSITION_5, &[44, 1486, 62379]);
+  }
+  #[test]
+  fn middlegame() {
+    assert_perft(POSITION_6, &[46, 2079, 89890]);
+  }
+
+  // small positions that each hit one edge case
+  #[test]
+  fn edge_cases() {
+    assert_perft_at("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", 6, 824064); // en passent that would leave the king in check
+    assert_perft_at("8/8/1k6/8/2pP4/8/5BK1/8 b - d3 0 1", 6, 824064);
+    assert_perft_at("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467); // en passent that gives check
+    assert_perft_at("8/5k2/8/2Pp4/2B5/1K6/8/8 w - d6 0 1", 6, 1440467);
+    assert_perft_at("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072); // short castling gives check
+    assert_perft_at("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711); // long castling gives check
+    assert_perft_at("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206); // castling, including losing the right to a rook capture
+    assert_perft_at("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476); // castling prevented
+    assert_perft_at("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001); // promoting out of check
+    assert_perft_at("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658); // discovered check
+    assert_perft_at("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342); // promoting to give check
+    assert_perft_at("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683); // underpromoting to give check
+    assert_perft_at("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217); // self stalemate
+    assert_perft_at("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584); // stalemate and checkmate
+    assert_perft_at("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527);
+  }
+
+  // perft that builds each node's moves out of the stages, so the stages have to add up to exactly the legal moves
+  fn staged_perft(board: &mut Board, depth: u32) -> u64 {
+    let mut moves = Vec::new();
+    if board.is_in_check() {
+      board.generate_evasions(&mut moves);
+    }
+    else {
+      board.generate_captures(&mut moves);
+      assert!(moves.iter().all(|m| m.is_capture()), "quiet move from generate_captures in {}", board.to_fen());
+      let captures = moves.len();
+      board.generate_quiets(&mut moves);
+      assert!(moves[captures..].iter().all(|m| !m.is_capture()), "capture from generate_quiets in {}", board.to_fen());
+      let legal_moves = moves.len();
+      board.generate_evasions(&mut moves);
+      assert_eq!(moves.len(), legal_moves, "evasion without a check in {}", board.to_fen());
+    }
+    if depth == 1 {
+      return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for piece_move in moves {
+      board.make_move(piece_move);
+      nodes += staged_perft(board, depth - 1);
+      board.unmake_move();
+    }
+    nodes
+  }
+
+  #[test]
+  fn staged_generation() {
+    for (fen, depth, expected) in [(KIWIPETE, 3, 97862), (POSITION_3, 4, 43238), (POSITION_4, 3, 9467), (POSITION_5, 3, 62379)] {
+      assert_eq!(staged_perft(&mut Board::new(fen), depth), expected, "staged perft({}) of {}", depth, fen);
+    }
+  }
+
+  // chess960 positions and node counts from the same page, castling rights are in Shredder-FEN and X-FEN
+  #[test]
+  fn chess960() {
+    let positions: [(&str, &[u64]); 6] = [
+      (KIWIPETE, &[48, 2039, 97862]), // castles are written differently, but the counts cant change
+      ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[21, 528, 12189, 326672]),
+      ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[21, 807, 18002, 667366]),
+      ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[20, 479, 10471, 273318]),
+      ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", &[22, 593, 13440, 382958]),
+      ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", &[28, 1120, 31058, 1171749]),
+    ];
+    for (fen, expected) in positions {
+      let mut board = Board::from_fen_chess960(fen).unwrap();
+      for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(board.perft(depth as u32 + 1), *nodes, "perft({}) of {}", depth + 1, fen);
+      }
+    }
+  }
+}
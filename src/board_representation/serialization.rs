@@ -0,0 +1,76 @@
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use crate::board_representation::{Board, Move};
+use crate::board_representation::notation::UciMove;
+
+// a board is written as its FEN, so the moves that led to it aren't kept. a chess960 board in the normal start position
+// looks the same as a normal one, so that has to be written down as well
+#[derive(Serialize, Deserialize)]
+struct BoardRecord {
+  fen: String,
+  #[serde(default)]
+  is_chess960: bool,
+}
+impl Serialize for Board {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    BoardRecord { fen: self.to_fen(), is_chess960: self.is_chess960 }.serialize(serializer)
+  }
+}
+impl<'de> Deserialize<'de> for Board {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let record = BoardRecord::deserialize(deserializer)?;
+    let board = if record.is_chess960 { Board::from_fen_chess960(&record.fen) } else { Board::from_fen(&record.fen) };
+    board.map_err(D::Error::custom)
+  }
+}
+
+// a move on its own is written in UCI, which can only be read back as a UciMove and then resolved with the board it was made on
+impl Serialize for Move {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_uci())
+  }
+}
+impl Serialize for UciMove {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_string())
+  }
+}
+impl<'de> Deserialize<'de> for UciMove {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board_representation::STARTING_FEN;
+  use crate::utils::{Color, PieceKind, Piece};
+
+  #[test]
+  fn json() {
+    let board = Board::new("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let json = serde_json::to_string(&board).unwrap();
+    assert_eq!(json, "{\"fen\":\"r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1\",\"is_chess960\":false}");
+    assert_eq!(serde_json::from_str::<Board>(&json).unwrap().get_hash(), board.get_hash());
+    assert_eq!(serde_json::from_str::<Board>(&format!("{{\"fen\":\"{}\"}}", STARTING_FEN)).unwrap().to_fen(), STARTING_FEN);
+    assert!(serde_json::from_str::<Board>("{\"fen\":\"8/8/8/8/8/8/8/8 w - - 0 1\"}").is_err());
+
+    // the normal start position only castles like chess960 if the flag comes back with it
+    let chess960 = serde_json::from_str::<Board>(&serde_json::to_string(&Board::new_chess960(518)).unwrap()).unwrap();
+    assert!(chess960.is_chess960());
+    assert_eq!(chess960.to_fen(), STARTING_FEN);
+
+    let promotion = board.parse_uci_move("b7a8n").unwrap();
+    let json = serde_json::to_string(&[promotion, board.parse_uci_move("e1g1").unwrap()]).unwrap();
+    assert_eq!(json, "[\"b7a8n\",\"e1g1\"]");
+    let uci_moves: Vec<UciMove> = serde_json::from_str(&json).unwrap();
+    assert_eq!(board.resolve_uci_move(uci_moves[0]), Ok(promotion));
+    assert_eq!(serde_json::to_string(&uci_moves).unwrap(), json);
+    assert!(serde_json::from_str::<UciMove>("\"b7a8k\"").is_err());
+
+    let knight = Piece::new(Color::White, PieceKind::Knight);
+    assert_eq!(serde_json::to_string(&knight).unwrap(), "{\"color\":\"White\",\"kind\":\"Knight\"}");
+    assert_eq!(serde_json::from_str::<Piece>("{\"color\":\"Black\",\"kind\":\"Queen\"}").unwrap(), Piece::new(Color::Black, PieceKind::Queen));
+  }
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+use crate::board_representation::{Board, Move, Square, Bitboard, Direction};
+use crate::board_representation::move_gen::king_moves;
+use crate::utils::{Color, PieceKind, Piece};
+
+// why a move from a front end was turned down, so it can be explained to whoever tried it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IllegalMoveReason {
+  NoPiece,
+  WrongColour,
+  CantMoveThere, // the piece doesn't move like that, or something is in the way
+  Pinned,
+  LeavesKingInCheck,
+  CastlingThroughAttack,
+  PromotionPieceRequired,
+  InvalidPromotion, // a promotion piece was given for a move that isn't a promotion, or it isn't a piece a pawn can become
+  ImpossiblePosition, // the board was edited into a position nothing can be played in
+}
+impl fmt::Display for IllegalMoveReason {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IllegalMoveReason::NoPiece => write!(f, "There's no piece on that square"),
+      IllegalMoveReason::WrongColour => write!(f, "That piece belongs to the other side"),
+      IllegalMoveReason::CantMoveThere => write!(f, "That piece can't move there"),
+      IllegalMoveReason::Pinned => write!(f, "That piece is pinned to its king"),
+      IllegalMoveReason::LeavesKingInCheck => write!(f, "That would leave the king in check"),
+      IllegalMoveReason::CastlingThroughAttack => write!(f, "The king can't castle out of, through or into check"),
+      IllegalMoveReason::PromotionPieceRequired => write!(f, "Pick a piece to promote to"),
+      IllegalMoveReason::InvalidPromotion => write!(f, "That isn't a promotion the pawn can make"),
+      IllegalMoveReason::ImpossiblePosition => write!(f, "The position has to be fixed before anything can move"),
+    }
+  }
+}
+impl std::error::Error for IllegalMoveReason {}
+
+// something about a position that could never come up in a game, which move generation and the bot can't cope with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PositionProblem {
+  WrongKingCount { color: Color, count: u32 },
+  PawnOnBackRank { square: Square },
+  OpponentInCheck, // the side that just moved left its king where it can be taken
+  ImpossibleCastling { color: Color, is_kingside: bool }, // the king or rook isn't on the square it castles from
+  ImpossibleEnPassant,
+}
+impl fmt::Display for PositionProblem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PositionProblem::WrongKingCount { color, count } => write!(f, "{} has {} kings instead of 1", color, count),
+      PositionProblem::PawnOnBackRank { square } => write!(f, "pawn on the back rank at {}", square),
+      PositionProblem::OpponentInCheck => write!(f, "the side not to move is in check"),
+      PositionProblem::ImpossibleCastling { color, is_kingside } => {
+        write!(f, "{} can castle {} without a king and rook to castle with", color, if *is_kingside { "kingside" } else { "queenside" })
+      },
+      PositionProblem::ImpossibleEnPassant => write!(f, "the en passant square isn't behind a pawn that just double pushed"),
+    }
+  }
+}
+
+// every problem with a position, not just the first one found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionError {
+  problems: Vec<PositionProblem>,
+}
+impl PositionError {
+  pub fn get_problems(&self) -> &[PositionProblem] {
+    &self.problems
+  }
+}
+impl fmt::Display for PositionError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let problems: Vec<String> = self.problems.iter().map(|problem| problem.to_string()).collect();
+    write!(f, "{}", problems.join(", "))
+  }
+}
+impl std::error::Error for PositionError {}
+
+impl Board {
+  pub fn validate(&self) -> Result<(), PositionError> {
+    let mut problems = Vec::new();
+
+    let mut has_both_kings = true;
+    for color in [Color::White, Color::Black] {
+      let count = self.pieces(color, PieceKind::King).count();
+      if count != 1 {
+        problems.push(PositionProblem::WrongKingCount { color, count });
+        has_both_kings = false;
+      }
+    }
+
+    let pawns = self.pieces_of_kind(PieceKind::Pawn);
+    for square in pawns & (Bitboard::RANK_1 | Bitboard::RANK_8) {
+      problems.push(PositionProblem::PawnOnBackRank { square });
+    }
+
+    // the rest needs a king to look at
+    if has_both_kings && self.is_square_attacked(self.king_square(!self.white_to_move), self.side_to_move()) {
+      problems.push(PositionProblem::OpponentInCheck);
+    }
+
+    for color in [Color::White, Color::Black] {
+      let (flags, squares) = if color.is_white() {
+        (self.white_castling_flags, self.white_castling_squares)
+      } else {
+        (self.black_castling_flags, self.black_castling_squares)
+      };
+      let (king, rook) = (Piece::new(color, PieceKind::King), Piece::new(color, PieceKind::Rook));
+      for (is_kingside, rook_moved, rook_square) in [(true, flags.rook_kingside_moved, squares.kingside_rook), (false, flags.rook_queenside_moved, squares.queenside_rook)] {
+        let has_right = !flags.king_moved && !rook_moved;
+        if has_right && (self.piece_at(squares.king) != Some(king) || self.piece_at(rook_square) != Some(rook)) {
+          problems.push(PositionProblem::ImpossibleCastling { color, is_kingside });
+        }
+      }
+    }
+
+    if self.en_passent_square.is_some_and(|square| !self.is_possible_en_passant(square)) {
+      problems.push(PositionProblem::ImpossibleEnPassant);
+    }
+
+    if problems.is_empty() { Ok(()) } else { Err(PositionError { problems }) }
+  }
+  // the square has to be directly behind a pawn that just double pushed, and nothing can be standing on it
+  pub(super) fn is_possible_en_passant(&self, square: Square) -> bool {
+    let expected_rank = if self.white_to_move { 5 } else { 2 };
+    let enemy_pawn = Piece::new(!self.side_to_move(), PieceKind::Pawn);
+    let passented_square = square.offset(Direction::forward(!self.white_to_move));
+    square.rank() == expected_rank && passented_square.and_then(|square| self.piece_at(square)) == Some(enemy_pawn) && self.piece_at(square).is_none()
+  }
+
+  pub fn is_legal(&self, piece_move: Move) -> bool {
+    self.get_moves(piece_move.get_start_square()).contains(&piece_move)
+  }
+
+  // turns a move picked on a board into the legal move it means, or says why it can't be played
+  pub fn validate_move(&self, start_square: Square, end_square: Square, promotion_kind: Option<PieceKind>) -> Result<Move, IllegalMoveReason> {
+    if !self.is_valid {
+      return Err(IllegalMoveReason::ImpossiblePosition);
+    }
+    let Some(piece) = self.piece_at(start_square) else {
+      return Err(IllegalMoveReason::NoPiece);
+    };
+    if piece.color != self.side_to_move() {
+      return Err(IllegalMoveReason::WrongColour);
+    }
+
+    let candidates: Vec<Move> = self.get_moves(start_square).into_iter().filter(|m| m.get_end_square() == end_square).collect();
+    if !candidates.is_empty() {
+      let is_promotion = candidates.iter().any(|m| m.is_promotion());
+      return match promotion_kind {
+        None if is_promotion => Err(IllegalMoveReason::PromotionPieceRequired),
+        Some(_) if !is_promotion => Err(IllegalMoveReason::InvalidPromotion),
+        _ => candidates.into_iter()
+          .find(|m| m.get_promotion_kind() == promotion_kind)
+          .ok_or(IllegalMoveReason::InvalidPromotion),
+      };
+    }
+
+    // it isn't legal, so work out what's wrong with it
+    let is_king = piece.kind == PieceKind::King;
+    if is_king {
+      for is_kingside in [true, false] {
+        let (_, rook_start, king_end, _) = self.castle_squares(self.white_to_move, is_kingside);
+        let is_castle_square = if self.is_chess960 { end_square == rook_start } else { end_square == king_end };
+        if is_castle_square && self.has_castling_right(is_kingside) && self.is_castling_path_clear(is_kingside) {
+          return Err(IllegalMoveReason::CastlingThroughAttack);
+        }
+      }
+
+      // get_legal_moves already leaves out the squares the king would be in check on
+      let own_pieces = self.get_colour_occupancy(self.side_to_move());
+      if (king_moves(start_square.bitboard()) & !own_pieces).contains(end_square) {
+        return Err(IllegalMoveReason::LeavesKingInCheck);
+      }
+      return Err(IllegalMoveReason::CantMoveThere);
+    }
+
+    // the squares it could reach if pins and checks didn't matter
+    if !self.get_legal_moves(start_square, piece, false).0.contains(end_square) {
+      return Err(IllegalMoveReason::CantMoveThere);
+    }
+    if self.pinned_pieces.contains(start_square) && !self.pin_rays[start_square.index()].contains(end_square) {
+      return Err(IllegalMoveReason::Pinned);
+    }
+    Err(IllegalMoveReason::LeavesKingInCheck) // doesn't get out of check, or takes en passent with both pawns in front of the king
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board_representation::FenError;
+
+  fn validate(board: &Board, start: &str, end: &str, promotion_kind: Option<PieceKind>) -> Result<String, IllegalMoveReason> {
+    board.validate_move(Square::from_algebraic(start).unwrap(), Square::from_algebraic(end).unwrap(), promotion_kind).map(|m| m.to_uci())
+  }
+
+  #[test]
+  fn legal_moves_come_back_whole() {
+    let board = Board::new("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_eq!(validate(&board, "e1", "g1", None), Ok("e1g1".to_string()));
+    assert_eq!(validate(&board, "b7", "a8", Some(PieceKind::Knight)), Ok("b7a8n".to_string()));
+    assert!(board.is_legal(board.validate_move(Square::from_algebraic("a1").unwrap(), Square::from_algebraic("a8").unwrap(), None).unwrap()));
+  }
+
+  #[test]
+  fn reasons() {
+    let board = Board::new("r3k2r/1P6/8/8/1b6/8/3N4/R3K2R w KQkq - 0 1");
+    assert_eq!(validate(&board, "e4", "e5", None), Err(IllegalMoveReason::NoPiece));
+    assert_eq!(validate(&board, "e8", "f8", None), Err(IllegalMoveReason::WrongColour));
+    assert_eq!(validate(&board, "a1", "b2", None), Err(IllegalMoveReason::CantMoveThere));
+    assert_eq!(validate(&board, "d2", "f3", None), Err(IllegalMoveReason::Pinned));
+    assert_eq!(validate(&board, "b7", "b8", None), Err(IllegalMoveReason::PromotionPieceRequired));
+    assert_eq!(validate(&board, "b7", "b8", Some(PieceKind::King)), Err(IllegalMoveReason::InvalidPromotion));
+    assert_eq!(validate(&board, "a1", "a7", Some(PieceKind::Queen)), Err(IllegalMoveReason::InvalidPromotion));
+
+    // a blocked castle is just a move the king can't make, but an attacked one gets its own reason
+    let board = Board::new("r3k3/8/8/8/8/8/8/RN2K2R w KQq - 0 1");
+    assert_eq!(validate(&board, "e1", "c1", None), Err(IllegalMoveReason::CantMoveThere));
+    let board = Board::new("2r1k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert_eq!(validate(&board, "e1", "c1", None), Err(IllegalMoveReason::CastlingThroughAttack));
+    assert_eq!(validate(&board, "e1", "g1", None), Ok("e1g1".to_string()));
+
+    // in check, anything that doesn't deal with it is rejected
+    let board = Board::new("4k3/8/8/8/4r3/8/3P4/4K3 w - - 0 1");
+    assert_eq!(validate(&board, "d2", "d3", None), Err(IllegalMoveReason::LeavesKingInCheck));
+    assert_eq!(validate(&board, "e1", "e2", None), Err(IllegalMoveReason::LeavesKingInCheck));
+  }
+
+  fn position_problems(fen: &str) -> Vec<PositionProblem> {
+    match Board::from_fen(fen) {
+      Err(FenError::InvalidPosition(error)) => error.get_problems().to_vec(),
+      Err(error) => panic!("{} didn't parse: {}", fen, error),
+      Ok(_) => Vec::new(),
+    }
+  }
+
+  #[test]
+  fn impossible_positions() {
+    assert_eq!(position_problems("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), vec![]);
+
+    // everything wrong gets listed, not just the first thing
+    assert_eq!(position_problems("8/8/8/8/8/8/8/P3K1K1 w - - 0 1"), vec![
+      PositionProblem::WrongKingCount { color: Color::White, count: 2 },
+      PositionProblem::WrongKingCount { color: Color::Black, count: 0 },
+      PositionProblem::PawnOnBackRank { square: Square::from_algebraic("a1").unwrap() },
+    ]);
+    assert_eq!(position_problems("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), vec![PositionProblem::OpponentInCheck]);
+    assert_eq!(position_problems("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1"), vec![]);
+  }
+}
@@ -0,0 +1,110 @@
+use crate::board_representation::{Board, Square};
+use crate::utils::Piece;
+
+// random numbers that get xored together to make a (nearly) unique key for a position
+pub struct ZobristKeys {
+  pieces: [[u64; 64]; 12],
+  black_to_move: u64,
+  castling: [u64; 4], // white kingside, white queenside, black kingside, black queenside
+  en_passent_files: [u64; 8],
+}
+
+// splitmix64, so the keys can be made at compile time without pulling in a rand crate
+const fn next_random(state: u64) -> (u64, u64) {
+  let state = state.wrapping_add(0x9E3779B97F4A7C15);
+  let mut z = state;
+  z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+  (state, z ^ (z >> 31))
+}
+const fn generate_keys() -> ZobristKeys {
+  let mut keys = ZobristKeys {
+    pieces: [[0; 64]; 12],
+    black_to_move: 0,
+    castling: [0; 4],
+    en_passent_files: [0; 8],
+  };
+  let mut state = 0xD0C4F15; // any seed works, this one is just fixed so keys are the same every run
+  let mut key;
+
+  let mut piece = 0;
+  while piece < 12 {
+    let mut square = 0;
+    while square < 64 {
+      (state, key) = next_random(state);
+      keys.pieces[piece][square] = key;
+      square += 1;
+    }
+    piece += 1;
+  }
+
+  (state, key) = next_random(state);
+  keys.black_to_move = key;
+
+  let mut i = 0;
+  while i < 4 {
+    (state, key) = next_random(state);
+    keys.castling[i] = key;
+    i += 1;
+  }
+
+  let mut i = 0;
+  while i < 8 {
+    (state, key) = next_random(state);
+    keys.en_passent_files[i] = key;
+    i += 1;
+  }
+
+  keys
+}
+
+pub static ZOBRIST_KEYS: ZobristKeys = generate_keys();
+
+pub fn piece_key(piece: Piece, square: Square) -> u64 {
+  ZOBRIST_KEYS.pieces[piece.index()][square.index()]
+}
+pub fn side_key() -> u64 {
+  ZOBRIST_KEYS.black_to_move
+}
+pub fn en_passent_key(en_passent_square: Option<Square>) -> u64 {
+  match en_passent_square {
+    Some(square) => ZOBRIST_KEYS.en_passent_files[square.file() as usize],
+    None => 0
+  }
+}
+
+impl Board {
+  pub(super) fn castling_key(&self) -> u64 {
+    let mut key = 0;
+    let rights = [
+      !self.white_castling_flags.king_moved && !self.white_castling_flags.rook_kingside_moved,
+      !self.white_castling_flags.king_moved && !self.white_castling_flags.rook_queenside_moved,
+      !self.black_castling_flags.king_moved && !self.black_castling_flags.rook_kingside_moved,
+      !self.black_castling_flags.king_moved && !self.black_castling_flags.rook_queenside_moved,
+    ];
+    for (i, has_right) in rights.iter().enumerate() {
+      if *has_right {
+        key ^= ZOBRIST_KEYS.castling[i];
+      }
+    }
+    key
+  }
+
+  // builds the key from nothing, make_move keeps it up to date without this
+  pub fn calculate_hash(&self) -> u64 {
+    let mut hash = 0;
+
+    for piece in Piece::iter() {
+      for square in self.bitboards[piece.index()] {
+        hash ^= piece_key(piece, square);
+      }
+    }
+    if !self.white_to_move {
+      hash ^= side_key();
+    }
+    hash ^= self.castling_key();
+    hash ^= en_passent_key(self.en_passent_square);
+
+    hash
+  }
+}
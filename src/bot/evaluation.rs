@@ -1,5 +1,5 @@
-use crate::board_representation::Board;
-use crate::utils::PieceType;
+use crate::board_representation::{Board, Square};
+use crate::utils::{Color, PieceKind, Piece};
 
 // names are slightly misleading, but they might as well be as they are as high as high can be (for 32 bit integers)
 pub const INFINITY: i32 = i32::MAX;
@@ -70,42 +70,17 @@ const QUEEN_PIECE_TABLE: [i32; 64] = [
   900,  900,  925,  950,  950,  925,  900,  900,
 ];
 
-fn get_piece_value(piece_type: PieceType, square_index: usize) -> i32 {
-  match piece_type {
-    PieceType::WhiteQueen => {
-      QUEEN_PIECE_TABLE[square_index]
-    },
-    PieceType::BlackQueen => {
-      -QUEEN_PIECE_TABLE[square_index]
-    },
-    PieceType::WhiteBishop => {
-      BISHOP_PIECE_TABLE[square_index]
-    },
-    PieceType::BlackBishop => {
-      -BISHOP_PIECE_TABLE[square_index]
-    },
-    PieceType::WhiteKnight => {
-      KNIGHT_PIECE_TABLE[square_index]
-    },
-    PieceType::BlackKnight => {
-      -KNIGHT_PIECE_TABLE[square_index]
-    },
-    PieceType::WhiteRook => {
-      ROOK_PIECE_TABLE[square_index]
-    },
-    PieceType::BlackRook => {
-      -ROOK_PIECE_TABLE[square_index]
-    },
-    PieceType::WhitePawn=> {
-      WHITE_PAWN_PIECE_TABLE[square_index]
-    },
-    PieceType::BlackPawn => {
-      -BLACK_PAWN_PIECE_TABLE[square_index]
-    },
-    _ => {
-      0
-    }
-  }
+fn get_piece_value(piece: Piece, square: Square) -> i32 { // black's pieces count against white
+  let square_index = square.index();
+  let value = match piece.kind {
+    PieceKind::Queen => QUEEN_PIECE_TABLE[square_index],
+    PieceKind::Bishop => BISHOP_PIECE_TABLE[square_index],
+    PieceKind::Knight => KNIGHT_PIECE_TABLE[square_index],
+    PieceKind::Rook => ROOK_PIECE_TABLE[square_index],
+    PieceKind::Pawn => if piece.is_white() { WHITE_PAWN_PIECE_TABLE[square_index] } else { BLACK_PAWN_PIECE_TABLE[square_index] },
+    PieceKind::King => 0,
+  };
+  if piece.is_white() { value } else { -value }
 }
 
 /*
@@ -119,35 +94,30 @@ things to add to evaluation:
 - penalty for split pawns
 - king safety
 */
-pub fn evaluate_position(board: Board, is_mate:bool, is_white: bool, depth: i32) -> i32 {
+pub fn evaluate_position(board: &Board, is_mate:bool, depth: i32) -> i32 {
   let bitboards = board.get_bitboards();
   let mut eval = 0;
-  if is_mate {
-    eval = NEGATIVE_INFINITY;
-
-    let increment = 5;
-    eval += increment * (STARTING_DEPTH - depth);
-    if !is_white {
-      eval *= 1;
+  if is_mate { // the side to move is the one that got mated
+    let increment = 5; // mates that happen sooner are worth more
+    if board.get_if_white_to_move() {
+      eval = NEGATIVE_INFINITY + increment * (STARTING_DEPTH - depth);
+    }
+    else {
+      eval = INFINITY - increment * (STARTING_DEPTH - depth);
     }
 
     return eval;
   }
 
-  for piece_type in PieceType::iter() {
-    let bitboard = bitboards[piece_type as usize];
-    for square_index in 0..64 {
-      if (1 << square_index) & bitboard == 0 {
-        continue;
-      }
-      eval += get_piece_value(piece_type, square_index);
+  for piece in Piece::iter() {
+    for square in bitboards[piece.index()] {
+      eval += get_piece_value(piece, square);
     }
   }
-  if bitboards[PieceType::WhiteBishop as usize].count_ones() >= 2 {
-    eval += BISHOP_PAIR_VALUE;
-  }
-  if bitboards[PieceType::BlackBishop as usize].count_ones() >= 2 {
-    eval -= BISHOP_PAIR_VALUE;
+  for color in [Color::White, Color::Black] {
+    if bitboards[Piece::new(color, PieceKind::Bishop).index()].count() >= 2 {
+      eval += if color.is_white() { BISHOP_PAIR_VALUE } else { -BISHOP_PAIR_VALUE };
+    }
   }
   eval
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-mod evaluation;
+pub mod evaluation;
 
 use std::cmp;
 use crate::board_representation::Board;
@@ -15,10 +15,20 @@ impl Bot {
     }
   }
 
-  fn minimax(&self, board: Board, depth: i32, mut alpha: i32, mut beta: i32, maximizing_player: bool) -> (i32, Option<Move>) { 
-    let is_mate = board.is_checkmate();
-    if depth == 0 || is_mate {
-      return (evaluate_position(board, is_mate, self.is_white_player, depth), None);
+  fn minimax(&self, board: &mut Board, depth: i32, mut alpha: i32, mut beta: i32, maximizing_player: bool) -> (i32, Option<Move>) { 
+    let moves = board.get_all_moves();
+    if moves.is_empty() {
+      if !board.is_in_check() {
+        return (0, None); // stalemate
+      }
+      return (evaluate_position(board, true, depth), None);
+    }
+    // a draw is worth nothing to either side, but the root still has to pick a move even if one could be claimed
+    if depth != STARTING_DEPTH && (board.is_insufficient_material() || board.is_threefold_repetition() || board.is_fifty_move_draw()) {
+      return (0, None);
+    }
+    if depth == 0 {
+      return (evaluate_position(board, false, depth), None);
     }
   
     let mut best_move: Option<Move> = None;
@@ -26,12 +36,11 @@ impl Bot {
     if maximizing_player {
       let mut max_eval = NEGATIVE_INFINITY;
   
-      for piece_move in board.get_all_moves() {
-        let mut iteration_board = board.clone();
-        iteration_board.make_move(piece_move);
-  
-        let (eval, _) = self.minimax(iteration_board, depth - 1, alpha, beta, false);
-        if eval > max_eval {
+      for &piece_move in &moves {
+        board.make_move(piece_move);
+        let (eval, _) = self.minimax(board, depth - 1, alpha, beta, false);
+        board.unmake_move();
+        if eval > max_eval || best_move.is_none() {
           max_eval = eval;
           best_move = Some(piece_move);
         }
@@ -42,17 +51,16 @@ impl Bot {
         }
       }
   
-      return (max_eval, best_move);
+      (max_eval, best_move)
     }
     else {
       let mut min_eval = INFINITY;
   
-      for piece_move in board.get_all_moves() {
-        let mut iteration_board = board.clone();
-        iteration_board.make_move(piece_move);
-  
-        let (eval, _) = self.minimax(iteration_board, depth - 1, alpha, beta, true);
-        if eval < min_eval {
+      for &piece_move in &moves {
+        board.make_move(piece_move);
+        let (eval, _) = self.minimax(board, depth - 1, alpha, beta, true);
+        board.unmake_move();
+        if eval < min_eval || best_move.is_none() {
           min_eval = eval;
           best_move = Some(piece_move);
         }
@@ -63,12 +71,31 @@ impl Bot {
         }
       }
   
-      return (min_eval, best_move);
+      (min_eval, best_move)
+    }
+  }
+
+  // the board is searched in place, but it's left exactly how it was given. None if there are no legal moves, or the position is impossible
+  pub fn get_best_move(&mut self, board: &mut Board) -> Option<Move> {
+    if !board.is_valid() {
+      return None;
     }
+    let (_score, best_move) = self.minimax(board, STARTING_DEPTH, NEGATIVE_INFINITY, INFINITY, self.is_white_player);
+    best_move
   }
+}
 
-  pub fn get_best_move(&mut self, board: Board) -> Move {
-    let (_score, best_move) = self.minimax(board, 5, NEGATIVE_INFINITY, INFINITY, self.is_white_player);
-    best_move.unwrap()
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn moves_in_claimable_draws() {
+    let mut board = Board::new("4k3/8/8/8/8/8/8/R3K3 b - - 100 80");
+    assert!(board.is_fifty_move_draw());
+    let best_move = Bot::new(false).get_best_move(&mut board).unwrap();
+    assert!(board.get_all_moves().contains(&best_move));
+
+    assert_eq!(Bot::new(true).get_best_move(&mut Board::new("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")), None);
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,325 @@
+use crate::board_representation::{Board, Move};
+use crate::pgn::{PgnGame, GameResult};
+#[cfg(feature = "serde")]
+use crate::pgn::PgnError;
+#[cfg(feature = "serde")]
+use crate::board_representation::notation::UciMove;
+
+// a game as a line of moves from a start position, with a place in that line that can be moved back and forward.
+// undone moves are kept until a different move is made, so they can be redone
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "GameRecord", try_from = "GameRecord"))]
+pub struct Game {
+  start_board: Board,
+  board: Board, // the position after the first ply moves
+  moves: Vec<Move>,
+  position_keys: Vec<u64>, // the hash of the start position, then the hash after each move
+  ply: usize,
+  result: GameResult, // the result at the current ply
+  decided_result: Option<(usize, GameResult)>, // a result the board can't see, like a resignation, and the ply it was decided at
+  tags: Vec<(String, String)>, // the same as a PGN's headers
+}
+impl Game {
+  pub fn new(start_board: Board) -> Self {
+    let mut game = Self {
+      board: start_board.clone(),
+      position_keys: vec![start_board.get_hash()],
+      start_board,
+      moves: Vec::new(),
+      ply: 0,
+      result: GameResult::Unknown,
+      decided_result: None,
+      tags: Vec::new(),
+    };
+    game.update_result();
+    game
+  }
+  pub fn from_pgn(pgn_game: &PgnGame) -> Self {
+    let mut game = Self::new(pgn_game.get_start_board());
+    for pgn_move in &pgn_game.moves {
+      game.make_move(pgn_move.piece_move);
+    }
+    game.tags = pgn_game.headers.clone();
+    if pgn_game.result != GameResult::Unknown {
+      game.set_result(pgn_game.result); // it might have been decided some other way, like a resignation
+    }
+    game
+  }
+  pub fn to_pgn(&self) -> PgnGame { // only has the moves up to the current ply
+    let mut pgn_game = PgnGame::new(&self.start_board, self.get_played_moves(), self.result);
+    pgn_game.headers = self.tags.clone();
+    pgn_game
+  }
+
+  // getters
+  pub fn get_board(&self) -> &Board {
+    &self.board
+  }
+  pub fn get_start_board(&self) -> &Board {
+    &self.start_board
+  }
+  pub fn get_ply(&self) -> usize {
+    self.ply
+  }
+  pub fn get_moves(&self) -> &[Move] { // every move in the line, including undone ones
+    &self.moves
+  }
+  pub fn get_played_moves(&self) -> &[Move] {
+    &self.moves[..self.ply]
+  }
+  pub fn get_position_keys(&self) -> &[u64] { // up to and including the current position
+    &self.position_keys[..=self.ply]
+  }
+  pub fn get_result(&self) -> GameResult {
+    self.result
+  }
+  // for results the board can't see, like resignations. it's kept for the current ply, so moving away and back doesn't lose it
+  pub fn set_result(&mut self, result: GameResult) {
+    self.decided_result = Some((self.ply, result));
+    self.result = result;
+  }
+  pub fn get_tag(&self, name: &str) -> Option<&str> {
+    self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+  }
+  pub fn set_tag(&mut self, name: &str, value: &str) {
+    match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+      Some(tag) => tag.1 = value.to_string(),
+      None => self.tags.push((name.to_string(), value.to_string())),
+    }
+  }
+
+  /* HISTORY */
+  // plays a legal move at the current ply. the undone moves are thrown away unless it's the next one of them
+  pub fn make_move(&mut self, piece_move: Move) {
+    if self.moves.get(self.ply) != Some(&piece_move) {
+      self.truncate_line(self.ply);
+      self.moves.push(piece_move);
+      self.board.make_move(piece_move);
+      self.position_keys.push(self.board.get_hash());
+    }
+    else {
+      self.board.make_move(piece_move);
+    }
+    self.ply += 1;
+    self.update_result();
+  }
+  pub fn undo(&mut self) -> bool { // false if there was nothing to undo
+    if self.ply == 0 {
+      return false;
+    }
+    self.board.unmake_move();
+    self.ply -= 1;
+    self.update_result();
+    true
+  }
+  pub fn redo(&mut self) -> bool {
+    let Some(&piece_move) = self.moves.get(self.ply) else {
+      return false;
+    };
+    self.board.make_move(piece_move);
+    self.ply += 1;
+    self.update_result();
+    true
+  }
+  pub fn goto(&mut self, ply: usize) -> bool { // false if the line isn't that long, the game is left where it was then
+    if ply > self.moves.len() {
+      return false;
+    }
+    while self.ply > ply {
+      self.undo();
+    }
+    while self.ply < ply {
+      self.redo();
+    }
+    true
+  }
+  // a copy of the game that stops at the ply, so another line can be tried without losing this one
+  pub fn branch_at(&self, ply: usize) -> Option<Game> {
+    if ply > self.moves.len() {
+      return None;
+    }
+    let mut branch = self.clone();
+    branch.goto(ply);
+    branch.truncate_line(ply);
+    Some(branch)
+  }
+
+  fn truncate_line(&mut self, ply: usize) { // throws away every move after the ply
+    self.moves.truncate(ply);
+    self.position_keys.truncate(ply + 1);
+    if self.decided_result.is_some_and(|(decided_ply, _)| decided_ply > ply) {
+      self.decided_result = None;
+    }
+  }
+  fn update_result(&mut self) {
+    self.result = match self.decided_result {
+      Some((ply, result)) if ply == self.ply => result,
+      _ => GameResult::from_status(self.board.status()),
+    };
+  }
+}
+
+// how a game is stored, the moves are in UCI so they're replayed from the start position to get the game back
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameRecord {
+  start_fen: String,
+  is_chess960: bool,
+  moves: Vec<UciMove>,
+  ply: usize,
+  decided_result: Option<(usize, GameResult)>, // any other result comes from the board
+  tags: Vec<(String, String)>,
+}
+#[cfg(feature = "serde")]
+impl From<Game> for GameRecord {
+  fn from(game: Game) -> Self {
+    Self {
+      start_fen: game.start_board.to_fen(),
+      is_chess960: game.start_board.is_chess960(),
+      moves: game.moves.iter().map(|&piece_move| UciMove::from(piece_move)).collect(),
+      ply: game.ply,
+      decided_result: game.decided_result,
+      tags: game.tags,
+    }
+  }
+}
+#[cfg(feature = "serde")]
+impl TryFrom<GameRecord> for Game {
+  type Error = PgnError;
+
+  fn try_from(record: GameRecord) -> Result<Self, PgnError> {
+    let start_board = if record.is_chess960 { Board::from_fen_chess960(&record.start_fen) } else { Board::from_fen(&record.start_fen) };
+    let mut game = Game::new(start_board.map_err(PgnError::InvalidFen)?);
+    for (i, &uci_move) in record.moves.iter().enumerate() {
+      let piece_move = game.board.resolve_uci_move(uci_move).map_err(|reason| PgnError::InvalidMove { ply: i + 1, san: uci_move.to_string(), reason })?;
+      game.make_move(piece_move);
+    }
+    game.decided_result = record.decided_result.filter(|(ply, _)| *ply <= game.moves.len());
+    game.goto(record.ply.min(game.moves.len()));
+    game.update_result();
+    game.tags = record.tags;
+    Ok(game)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board_representation::STARTING_FEN;
+  use crate::pgn::parse_pgn;
+
+  fn play(game: &mut Game, sans: &[&str]) {
+    for san in sans {
+      let piece_move = game.get_board().parse_san(san).unwrap();
+      game.make_move(piece_move);
+    }
+  }
+
+  #[test]
+  fn undo_and_redo() {
+    let mut game = Game::new(Board::new(STARTING_FEN));
+    play(&mut game, &["e4", "e5", "Nf3"]);
+    let after_nf3 = game.get_board().to_fen();
+
+    assert!(game.undo() && game.undo());
+    assert_eq!(game.get_ply(), 1);
+    assert_eq!(game.get_moves().len(), 3);
+    assert!(game.redo() && game.redo());
+    assert!(!game.redo());
+    assert_eq!(game.get_board().to_fen(), after_nf3);
+
+    assert!(game.goto(0));
+    assert_eq!(game.get_board().to_fen(), STARTING_FEN);
+    assert!(!game.undo());
+    assert!(!game.goto(4));
+    assert_eq!(game.get_position_keys(), [Board::new(STARTING_FEN).get_hash()]);
+
+    // replaying the next move keeps the line, anything else replaces it
+    play(&mut game, &["e4"]);
+    assert_eq!(game.get_moves().len(), 3);
+    play(&mut game, &["c5"]);
+    assert_eq!(game.get_moves().len(), 2);
+    assert!(!game.redo());
+    assert_eq!(game.get_position_keys().len(), 3);
+    assert_eq!(game.get_position_keys()[2], game.get_board().get_hash());
+  }
+
+  #[test]
+  fn branches() {
+    let mut game = Game::new(Board::new(STARTING_FEN));
+    play(&mut game, &["f3", "e5", "g4", "Qh4#"]);
+    assert_eq!(game.get_result(), GameResult::BlackWins);
+
+    let mut branch = game.branch_at(2).unwrap();
+    assert_eq!(branch.get_result(), GameResult::Unknown);
+    play(&mut branch, &["e4"]);
+    assert_eq!(branch.get_moves().len(), 3);
+    assert_eq!(game.get_moves().len(), 4);
+    assert!(game.branch_at(5).is_none());
+
+    game.set_tag("White", "Patzer");
+    let reread = Game::from_pgn(&parse_pgn(&game.to_pgn().to_pgn()).unwrap()[0]);
+    assert_eq!(reread.get_played_moves(), game.get_played_moves());
+    assert_eq!(reread.get_tag("White"), Some("Patzer"));
+    assert_eq!(reread.get_result(), GameResult::BlackWins);
+  }
+
+  #[test]
+  fn decided_results_are_kept() {
+    let mut game = Game::from_pgn(&parse_pgn("[Result \"0-1\"]\n\n1. e4 e5 0-1").unwrap()[0]);
+    assert_eq!(game.get_result(), GameResult::BlackWins);
+    assert!(game.goto(1) && game.goto(2));
+    assert_eq!(game.get_result(), GameResult::BlackWins);
+    game.undo();
+    assert_eq!(game.get_result(), GameResult::Unknown);
+
+    // a game only has one result, so deciding it on another ply moves it there
+    game.set_result(GameResult::WhiteWins);
+    game.redo();
+    assert_eq!(game.get_result(), GameResult::Unknown);
+    game.undo();
+    assert_eq!(game.get_result(), GameResult::WhiteWins);
+
+    // and it goes if the line it was on is replaced
+    game.goto(0);
+    play(&mut game, &["d4"]);
+    assert_eq!(game.get_result(), GameResult::Unknown);
+    game.undo();
+    assert_eq!(game.get_result(), GameResult::Unknown);
+
+    let mut game = Game::new(Board::new(STARTING_FEN));
+    play(&mut game, &["e4", "e5"]);
+    game.set_result(GameResult::Draw);
+    assert_eq!(game.branch_at(1).unwrap().get_result(), GameResult::Unknown);
+    play(&mut game.branch_at(2).unwrap(), &["Nf3"]);
+    assert_eq!(game.branch_at(2).unwrap().get_result(), GameResult::Draw);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn json() {
+    let mut game = Game::new(Board::new_chess960(518));
+    play(&mut game, &["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O"]);
+    game.undo();
+    game.set_tag("Event", "Casual game");
+
+    let json = serde_json::to_string(&game).unwrap();
+    assert!(json.contains("\"moves\":[\"e2e4\",\"e7e5\",\"g1f3\",\"b8c6\",\"f1c4\",\"f8c5\",\"e1h1\"]"));
+    let reread: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(reread.get_moves(), game.get_moves());
+    assert_eq!(reread.get_ply(), 6);
+    assert_eq!(reread.get_board().get_hash(), game.get_board().get_hash());
+    assert_eq!(reread.get_tag("Event"), Some("Casual game"));
+
+    game.redo();
+    game.set_result(GameResult::WhiteWins);
+    game.undo();
+    let mut reread: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+    assert_eq!(reread.get_result(), GameResult::Unknown);
+    reread.redo();
+    assert_eq!(reread.get_result(), GameResult::WhiteWins);
+
+    let illegal = json.replace("e1h1", "e1e3");
+    assert!(serde_json::from_str::<Game>(&illegal).err().unwrap().to_string().contains("bad move 'e1e3' at ply 7"));
+  }
+}
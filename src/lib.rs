@@ -0,0 +1,6 @@
+// the engine, which has nothing to do with drawing so it can be used without a window. the gui is in main.rs behind the gui feature
+pub mod board_representation;
+pub mod bot;
+pub mod utils;
+pub mod pgn;
+pub mod game;
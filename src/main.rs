@@ -1,18 +1,29 @@
 /* MODULES */
 mod rendering;
-mod board_representation;
-mod bot;
-mod utils;
 
 /* IMPORTS */
 use rendering::piece_sprite::*;
 use rendering::square::*;
-use board_representation::*;
-use bot::Bot;
-use utils::*;
+use rendering::square::Square; // the board has its own Square, but the gui only ever means this one
+use chess::board_representation::{self, *};
+use chess::bot::Bot;
+use chess::game::Game;
+use chess::utils::*;
 use macroquad::prelude::*;
 
-const FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const FEN: &str = STARTING_FEN;
+const PGN_PATH: &str = "last_game.pgn";
+
+// writes the game out so it isnt lost when the window closes, this does nothing on the web as there's no file system
+fn save_game(game: &Game) {
+  if let Err(error) = std::fs::write(PGN_PATH, game.to_pgn().to_pgn()) {
+    eprintln!("couldn't save the game to {}: {}", PGN_PATH, error);
+  }
+}
+
+fn board_square(square_index: i32) -> board_representation::Square { // the sprites and the grid index squares the same way the board does
+  board_representation::Square::new(square_index as u8)
+}
 
 fn window_conf() -> Conf {
   Conf {
@@ -26,9 +37,21 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-  let mut board = Board::new(FEN);
+  // castle in chess960 by dropping the king on the rook
+  let board = if std::env::args().any(|arg| arg == "--chess960") {
+    rand::srand(miniquad::date::now() as u64);
+    Board::new_chess960(rand::gen_range(0, 960))
+  } else {
+    Board::new(FEN)
+  };
+  let mut game = Game::new(board);
+  game.set_tag("Event", "Casual game");
+  game.set_tag("Site", "duckfish");
+  game.set_tag("White", "Player");
+  game.set_tag("Black", "duckfish");
   let mut bot = Bot::new(false);
-  let mut piece_moves: Vec<Move> = Vec::new();
+  let mut saved_game = false;
+  let mut rejected_move: Option<IllegalMoveReason> = None; // shown until the next move that works
 
   let texture_atlas = load_texture(TEXTURE_PATH).await.unwrap();
 
@@ -37,22 +60,20 @@ async fn main() {
 
 
   let mut piece_sprites: Vec<PieceSprite> = Vec::new();
-  for piece_type in PieceType::iter() {
-    for i in 0..64 {
-      if board.get_bitboards()[piece_type as usize] & (1 << i) != 0 {
-        let new_piece = PieceSprite::new(squares[0].rect.w, &texture_atlas, piece_type, i);
-        piece_sprites.push(new_piece);
-      }
+  for square in board_representation::Square::all() {
+    if let Some(piece) = game.get_board().piece_at(square) {
+      let new_piece = PieceSprite::new(squares[0].rect.w, &texture_atlas, piece, square.index() as i32);
+      piece_sprites.push(new_piece);
     }
   }
 
   // square grid setup
   let mut x = 7;
   let mut y = 7;
-  for i in 0..64 {
-    squares[i].set_location(x as f32 * squares[i].rect.w, y as f32 * squares[i].rect.w);
+  for square in squares.iter_mut() {
+    square.set_location(x as f32 * square.rect.w, y as f32 * square.rect.w);
     if (x + y) % 2 == 0 {
-      squares[i].set_colour(LIGHTSQUARE);
+      square.set_colour(LIGHTSQUARE);
     }
 
     x -= 1;
@@ -62,9 +83,25 @@ async fn main() {
     }
   }
 
+  prevent_quit(); // so the game can be saved first
   loop {
     clear_background(GRAY);
 
+    // take back or replay the player's move and the bot's reply together, so it's always the player's turn after
+    if is_key_pressed(KeyCode::Left) && game.undo() {
+      if !game.get_board().get_if_white_to_move() {
+        game.undo();
+      }
+      rejected_move = None;
+      saved_game = false; // the game can carry on differently from here
+    }
+    if is_key_pressed(KeyCode::Right) && game.redo() {
+      if !game.get_board().get_if_white_to_move() {
+        game.redo();
+      }
+      rejected_move = None;
+    }
+
     for square in &squares {
       if square.handle_mouseover() {
         mouse_square = *square;
@@ -74,8 +111,10 @@ async fn main() {
     }
 
     let mut piecesprites_to_remove: Vec<usize> = Vec::new();
+    let status = game.get_board().status();
+    let is_game_over = status != GameStatus::Ongoing; // no more moves can be made once the game has ended
 
-    piece_sprites.sort_by(|a, b| a.get_if_mouseonsprite().cmp(&b.get_if_mouseonsprite())); // sorts the list so that the pieces that are affected by the mouse are last. this ensures that they are drawn on top of the other pieces
+    piece_sprites.sort_by_key(|a| a.get_if_mouseonsprite()); // sorts the list so that the pieces that are affected by the mouse are last. this ensures that they are drawn on top of the other pieces
     for (i, piece_sprite) in piece_sprites.iter_mut(  ).enumerate() {
       piece_sprite.handle_mousedown();
 
@@ -84,43 +123,51 @@ async fn main() {
         let (mouse_x, mouse_y) = mouse_position();
         piece_sprite.rect.x = mouse_x - (piece_sprite.rect.w / 2.0);
         piece_sprite.rect.y = mouse_y - (piece_sprite.rect.w / 2.0);
-
-        let moves = board.get_moves(piece_sprite.get_square());
-        piece_moves = moves.clone();
       }
 
-      else if piece_sprite.moved_piece && is_mouse_button_released(MouseButton::Left) { // make a move
+      else if piece_sprite.moved_piece && is_mouse_button_released(MouseButton::Left) && !is_game_over { // make a move
         let mouse_square_index = squares.iter().position(|&r| r == mouse_square).unwrap() as i32;
-        let mut piece_move = Move::new(piece_sprite.get_square(), mouse_square_index, piece_sprite.get_piecetype(), MoveFlags::new());
+        let start_square = board_square(piece_sprite.get_square());
+        let end_square = board_square(mouse_square_index);
+        let is_pawn = piece_sprite.get_piece().kind == PieceKind::Pawn;
+        let mut promotion_kind = None;
         
-        if mouse_square_index > 55 || mouse_square_index < 8 {
+        if is_pawn && !(8..=55).contains(&mouse_square_index) {
           if is_key_down(KeyCode::N) || is_key_down(KeyCode::K) {
-            piece_move.promotion_piece = if board.get_if_white_to_move() { Some(PieceType::WhiteKnight) } else { Some(PieceType::BlackKnight) };
+            promotion_kind = Some(PieceKind::Knight);
           }
           else if is_key_down(KeyCode::B) {
-            piece_move.promotion_piece = if board.get_if_white_to_move() { Some(PieceType::WhiteBishop) } else { Some(PieceType::BlackBishop) };
+            promotion_kind = Some(PieceKind::Bishop);
           }
           else if is_key_down(KeyCode::R) {
-            piece_move.promotion_piece = if board.get_if_white_to_move() { Some(PieceType::WhiteRook) } else { Some(PieceType::BlackRook) };
+            promotion_kind = Some(PieceKind::Rook);
           }
           else if is_key_down(KeyCode::Q) {
-            piece_move.promotion_piece = if board.get_if_white_to_move() { Some(PieceType::WhiteQueen) } else { Some(PieceType::BlackQueen) };
+            promotion_kind = Some(PieceKind::Queen);
           }
         }
 
-        if let Some(matching_move) = piece_moves.iter().find(|m| **m == piece_move) { // finds move in the list of legal moves
-          board.make_move(matching_move.clone());
-          
-          let bot_move = bot.get_best_move(board.clone());
-          board.make_move(bot_move);
+        if start_square != end_square { // dropping a piece back where it came from isnt a move
+          match game.get_board().validate_move(start_square, end_square, promotion_kind) {
+            Ok(player_move) => {
+              rejected_move = None;
+              game.make_move(player_move);
+              
+              if game.get_board().status() == GameStatus::Ongoing {
+                if let Some(bot_move) = bot.get_best_move(&mut game.get_board().clone()) {
+                  game.make_move(bot_move);
+                }
+              }
+            },
+            Err(reason) => rejected_move = Some(reason),
+          }
         }
 
         piece_sprite.moved_piece = false;
       }
   
       else if piece_sprite.get_square() != -1 { // set the sprites locations to the square
-        let piecetype_squares = bits_to_indices(&board.get_bitboards()[piece_sprite.get_piecetype() as usize]);
-        if !piecetype_squares.contains(&piece_sprite.get_square()) { // if the piece doesnt exist there, add it to a vector to be removed
+        if game.get_board().piece_at(board_square(piece_sprite.get_square())) != Some(piece_sprite.get_piece()) { // if the piece doesnt exist there, add it to a vector to be removed
           piecesprites_to_remove.push(i);
         }
 
@@ -140,15 +187,39 @@ async fn main() {
       piece_sprites.remove(index);
     }
 
-    for piece_type in PieceType::iter() { // add the piece if it doesnt exist
-      let piecetype_squares = bits_to_indices(&board.get_bitboards()[piece_type as usize]);
-  
-      for square_index in piecetype_squares {
-        if !piece_sprites.iter().any(|sprite| sprite.get_square() == square_index && sprite.get_piecetype() == piece_type) { // if the piece doesnt exist
-          let new_piece_sprite: PieceSprite = PieceSprite::new(squares[0].rect.w, &texture_atlas, piece_type, square_index);
-          piece_sprites.push(new_piece_sprite);
-        }
+    for square in board_representation::Square::all() { // add the piece if it doesnt exist
+      let Some(piece) = game.get_board().piece_at(square) else {
+        continue;
+      };
+      let square_index = square.index() as i32;
+      if !piece_sprites.iter().any(|sprite| sprite.get_square() == square_index && sprite.get_piece() == piece) { // if the piece doesnt exist
+        let new_piece_sprite: PieceSprite = PieceSprite::new(squares[0].rect.w, &texture_atlas, piece, square_index);
+        piece_sprites.push(new_piece_sprite);
+      }
+    }
+
+    if let Some(reason) = rejected_move {
+      let message = reason.to_string();
+      let text_size = measure_text(&message, None, 32, 1.0);
+      draw_text(&message, (screen_width() - text_size.width) / 2.0, text_size.height + 8.0, 32.0, BLACK);
+    }
+
+    if is_game_over {
+      let message = status.to_string();
+      let text_size = measure_text(&message, None, 48, 1.0);
+      draw_text(&message, (screen_width() - text_size.width) / 2.0, screen_height() / 2.0, 48.0, BLACK);
+
+      if !saved_game {
+        save_game(&game);
+        saved_game = true;
+      }
+    }
+
+    if is_quit_requested() {
+      if !saved_game && !game.get_moves().is_empty() {
+        save_game(&game);
       }
+      break;
     }
 
     next_frame().await;
@@ -0,0 +1,582 @@
+use std::fmt;
+use crate::board_representation::{Board, Move, GameStatus, FenError, STARTING_FEN};
+use crate::board_representation::notation::NotationError;
+use crate::utils::Color;
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [ // the tags every PGN game has to start with, and what they are when nobody knows
+  ("Event", "?"),
+  ("Site", "?"),
+  ("Date", "????.??.??"),
+  ("Round", "?"),
+  ("White", "?"),
+  ("Black", "?"),
+  ("Result", "*"),
+];
+const LINE_LENGTH: usize = 80;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+  WhiteWins,
+  BlackWins,
+  Draw,
+  Unknown
+}
+impl GameResult {
+  pub fn from_status(status: GameStatus) -> Self {
+    match status {
+      GameStatus::Ongoing | GameStatus::ImpossiblePosition => GameResult::Unknown,
+      GameStatus::Checkmate(Color::White) => GameResult::WhiteWins,
+      GameStatus::Checkmate(Color::Black) => GameResult::BlackWins,
+      _ => GameResult::Draw,
+    }
+  }
+  fn from_token(token: &str) -> Option<Self> {
+    match token {
+      "1-0" => Some(GameResult::WhiteWins),
+      "0-1" => Some(GameResult::BlackWins),
+      "1/2-1/2" => Some(GameResult::Draw),
+      "*" => Some(GameResult::Unknown),
+      _ => None
+    }
+  }
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      GameResult::WhiteWins => "1-0",
+      GameResult::BlackWins => "0-1",
+      GameResult::Draw => "1/2-1/2",
+      GameResult::Unknown => "*",
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PgnError {
+  InvalidTag(String),
+  InvalidFen(FenError),
+  InvalidMove { ply: usize, san: String, reason: NotationError }, // ply counts from 1 for the first move of the game
+  UnterminatedComment,
+  UnterminatedVariation,
+  UnexpectedCharacter(char), // a closing bracket or brace that nothing opened
+}
+impl fmt::Display for PgnError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PgnError::InvalidTag(tag) => write!(f, "invalid tag pair '{}'", tag),
+      PgnError::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+      PgnError::InvalidMove { ply, san, reason } => write!(f, "bad move '{}' at ply {}: {}", san, ply, reason),
+      PgnError::UnterminatedComment => write!(f, "comment is missing its closing brace"),
+      PgnError::UnterminatedVariation => write!(f, "variation is missing its closing parenthesis"),
+      PgnError::UnexpectedCharacter(c) => write!(f, "unexpected '{}' in the movetext", c),
+    }
+  }
+}
+impl std::error::Error for PgnError {}
+
+#[derive(Clone)]
+pub struct PgnMove {
+  pub piece_move: Move,
+  pub san: String,
+  pub nags: Vec<u8>, // numeric annotation glyphs, $1 is "!", $2 is "?" and so on
+  pub comment: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct PgnGame {
+  pub headers: Vec<(String, String)>, // in the order they were read, the seven tag roster is always written first no matter what
+  pub start_fen: String,
+  pub is_chess960: bool,
+  pub comment: Option<String>, // a comment before the first move
+  pub moves: Vec<PgnMove>,
+  pub result: GameResult,
+}
+impl PgnGame {
+  // a game from a list of moves played from a position, like the ones made in the gui
+  pub fn new(start_board: &Board, moves: &[Move], result: GameResult) -> Self {
+    let mut board = start_board.clone();
+    let mut pgn_moves = Vec::new();
+    for piece_move in moves {
+      pgn_moves.push(PgnMove {
+        piece_move: *piece_move,
+        san: board.move_to_san(*piece_move),
+        nags: Vec::new(),
+        comment: None,
+      });
+      board.make_move(*piece_move);
+    }
+
+    Self {
+      headers: Vec::new(),
+      start_fen: start_board.to_fen(),
+      is_chess960: start_board.is_chess960(),
+      comment: None,
+      moves: pgn_moves,
+      result,
+    }
+  }
+
+  pub fn get_header(&self, name: &str) -> Option<&str> {
+    self.headers.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+  }
+  pub fn set_header(&mut self, name: &str, value: &str) {
+    match self.headers.iter_mut().find(|(tag, _)| tag == name) {
+      Some(header) => header.1 = value.to_string(),
+      None => self.headers.push((name.to_string(), value.to_string())),
+    }
+  }
+
+  pub fn get_start_board(&self) -> Board {
+    let board = if self.is_chess960 { Board::from_fen_chess960(&self.start_fen) } else { Board::from_fen(&self.start_fen) };
+    board.expect("the start FEN is checked when the game is made")
+  }
+  // the position at the end of the game
+  pub fn get_board(&self) -> Board {
+    let mut board = self.get_start_board();
+    for pgn_move in &self.moves {
+      board.make_move(pgn_move.piece_move);
+    }
+    board
+  }
+
+  pub fn to_pgn(&self) -> String {
+    let mut pgn = String::new();
+
+    // headers
+    let mut headers: Vec<(String, String)> = SEVEN_TAG_ROSTER.iter()
+      .map(|(tag, default)| (tag.to_string(), self.get_header(tag).unwrap_or(default).to_string()))
+      .collect();
+    headers[6].1 = self.result.as_str().to_string();
+    for (tag, value) in &self.headers {
+      if !SEVEN_TAG_ROSTER.iter().any(|(roster_tag, _)| roster_tag == tag) && !matches!(tag.as_str(), "Variant" | "SetUp" | "FEN") {
+        headers.push((tag.clone(), value.clone()));
+      }
+    }
+    if self.is_chess960 {
+      headers.push(("Variant".to_string(), "Chess960".to_string()));
+    }
+    if self.is_chess960 || self.start_fen != STARTING_FEN {
+      headers.push(("SetUp".to_string(), "1".to_string()));
+      headers.push(("FEN".to_string(), self.start_fen.clone()));
+    }
+    for (tag, value) in headers {
+      pgn.push_str(&format!("[{} \"{}\"]\n", tag, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    pgn.push('\n');
+
+    // movetext
+    let mut white_to_move = self.get_start_board().get_if_white_to_move();
+    let mut move_number = self.start_fen.split(' ').nth(5).and_then(|n| n.parse().ok()).unwrap_or(1);
+
+    let mut tokens = Vec::new();
+    if let Some(comment) = &self.comment {
+      tokens.push(format!("{{{}}}", comment));
+    }
+    let mut needs_number = true; // black's moves only get a number at the start or after a comment
+    for pgn_move in &self.moves {
+      if white_to_move {
+        tokens.push(format!("{}.", move_number));
+      }
+      else if needs_number {
+        tokens.push(format!("{}...", move_number));
+      }
+      tokens.push(pgn_move.san.clone());
+      for nag in &pgn_move.nags {
+        tokens.push(format!("${}", nag));
+      }
+      needs_number = false;
+      if let Some(comment) = &pgn_move.comment {
+        tokens.push(format!("{{{}}}", comment));
+        needs_number = true;
+      }
+
+      if !white_to_move {
+        move_number += 1;
+      }
+      white_to_move = !white_to_move;
+    }
+    tokens.push(self.result.as_str().to_string());
+
+    // wrap the movetext so no line is longer than 80 characters
+    let mut line_length = 0;
+    for token in tokens {
+      if line_length != 0 && line_length + 1 + token.len() > LINE_LENGTH {
+        pgn.push('\n');
+        line_length = 0;
+      }
+      if line_length != 0 {
+        pgn.push(' ');
+        line_length += 1;
+      }
+      line_length += token.len();
+      pgn.push_str(&token);
+    }
+    pgn.push('\n');
+
+    pgn
+  }
+}
+
+/* PARSING */
+enum Token {
+  Tag(String, String),
+  Comment(String),
+  Nag(u8),
+  Move(String),
+  Result(GameResult),
+  VariationStart,
+  VariationEnd,
+}
+
+fn parse_tag(text: &str) -> Result<Token, PgnError> {
+  let invalid = || PgnError::InvalidTag(text.to_string());
+
+  let (name, rest) = text.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+  let rest = rest.trim();
+  if !rest.starts_with('"') || !rest.ends_with('"') || rest.len() < 2 {
+    return Err(invalid());
+  }
+
+  let mut value = String::new();
+  let mut chars = rest[1..rest.len() - 1].chars();
+  while let Some(c) = chars.next() {
+    if c == '\\' {
+      value.push(chars.next().ok_or_else(invalid)?);
+    }
+    else {
+      value.push(c);
+    }
+  }
+  Ok(Token::Tag(name.to_string(), value))
+}
+
+// splits off suffix annotations like "!?" and turns them into the NAG they stand for
+fn split_annotation(word: &str) -> (&str, Option<u8>) {
+  let san = word.trim_end_matches(['!', '?']);
+  let nag = match &word[san.len()..] {
+    "!" => Some(1),
+    "?" => Some(2),
+    "!!" => Some(3),
+    "??" => Some(4),
+    "!?" => Some(5),
+    "?!" => Some(6),
+    _ => None,
+  };
+  (san, nag)
+}
+
+// where the ] that closes a tag is, skipping any inside its quoted value
+fn tag_end(chars: &[char]) -> Option<usize> {
+  let mut in_quotes = false;
+  let mut escaped = false;
+  for (i, &c) in chars.iter().enumerate() {
+    match c {
+      _ if escaped => escaped = false,
+      '\\' if in_quotes => escaped = true,
+      '"' => in_quotes = !in_quotes,
+      ']' if !in_quotes => return Some(i),
+      _ => {}
+    }
+  }
+  None
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+  let mut tokens = Vec::new();
+  let chars: Vec<char> = text.chars().collect();
+  let mut i = 0;
+  let mut line_start = true;
+
+  while i < chars.len() {
+    let c = chars[i];
+    if c == '%' && line_start { // escaped line, meant for other programs
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+      continue;
+    }
+    line_start = c == '\n';
+
+    match c {
+      _ if c.is_whitespace() => i += 1,
+      '[' => {
+        let end = tag_end(&chars[i..]).ok_or_else(|| PgnError::InvalidTag(chars[i..].iter().collect()))?;
+        let tag: String = chars[i + 1..i + end].iter().collect();
+        tokens.push(parse_tag(&tag)?);
+        i += end + 1;
+      },
+      '{' => {
+        let end = chars[i..].iter().position(|c| *c == '}').ok_or(PgnError::UnterminatedComment)?;
+        let comment: String = chars[i + 1..i + end].iter().collect();
+        tokens.push(Token::Comment(comment.trim().to_string()));
+        i += end + 1;
+      },
+      ';' => {
+        let end = chars[i..].iter().position(|c| *c == '\n').unwrap_or(chars.len() - i);
+        let comment: String = chars[i + 1..i + end].iter().collect();
+        tokens.push(Token::Comment(comment.trim().to_string()));
+        i += end;
+      },
+      '(' => {
+        tokens.push(Token::VariationStart);
+        i += 1;
+      },
+      ')' => {
+        tokens.push(Token::VariationEnd);
+        i += 1;
+      },
+      '}' | ']' => return Err(PgnError::UnexpectedCharacter(c)),
+      '$' => {
+        let digits: String = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(nag) = digits.parse() {
+          tokens.push(Token::Nag(nag));
+        }
+        i += 1 + digits.len();
+      },
+      _ => {
+        let word: String = chars[i..].iter().take_while(|c| !c.is_whitespace() && !"[]{}();$".contains(**c)).collect();
+        i += word.chars().count();
+
+        if let Some(result) = GameResult::from_token(&word) {
+          tokens.push(Token::Result(result));
+          continue;
+        }
+        if word == "e.p." { // written apart from the capture it belongs to, which says all of it already
+          continue;
+        }
+        // move numbers, which can be stuck to the move like "1.e4"
+        let san = word.trim_start_matches(|c: char| c.is_ascii_digit()).trim_start_matches('.');
+        if san.len() == word.len() || word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.') {
+          if san.is_empty() {
+            continue;
+          }
+          let (san, nag) = split_annotation(san);
+          tokens.push(Token::Move(san.to_string()));
+          if let Some(nag) = nag {
+            tokens.push(Token::Nag(nag));
+          }
+        }
+        else {
+          tokens.push(Token::Move(word)); // something like 0-0 that starts with a digit but isnt a move number
+        }
+      }
+    }
+  }
+
+  Ok(tokens)
+}
+
+struct GameBuilder {
+  game: PgnGame,
+  board: Option<Board>,
+}
+impl GameBuilder {
+  fn new() -> Self {
+    Self {
+      game: PgnGame {
+        headers: Vec::new(),
+        start_fen: STARTING_FEN.to_string(),
+        is_chess960: false,
+        comment: None,
+        moves: Vec::new(),
+        result: GameResult::Unknown,
+      },
+      board: None,
+    }
+  }
+  fn has_content(&self) -> bool {
+    !self.game.headers.is_empty() || !self.game.moves.is_empty() || self.game.comment.is_some()
+  }
+  fn board(&mut self) -> Result<&mut Board, PgnError> { // made on the first move, once all the tags (and maybe a FEN) have been read
+    if self.board.is_none() {
+      if let Some(fen) = self.game.get_header("FEN") {
+        self.game.start_fen = fen.to_string();
+      }
+      let variant = self.game.get_header("Variant").unwrap_or("").to_lowercase();
+      let board = if variant.contains("960") || variant.contains("fischerandom") {
+        Board::from_fen_chess960(&self.game.start_fen)
+      } else {
+        Board::from_fen(&self.game.start_fen)
+      };
+      let board = board.map_err(PgnError::InvalidFen)?;
+      self.game.is_chess960 = board.is_chess960();
+      self.board = Some(board);
+    }
+    Ok(self.board.as_mut().unwrap())
+  }
+  fn finish(mut self) -> Result<PgnGame, PgnError> {
+    self.board()?; // makes sure a FEN tag on a game without moves is still checked
+    Ok(self.game)
+  }
+}
+
+// reads every game in a PGN file, variations are skipped
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+  let mut games = Vec::new();
+  let mut builder = GameBuilder::new();
+  let mut variation_depth = 0;
+
+  for token in tokenize(text)? {
+    if variation_depth > 0 {
+      match token {
+        Token::VariationStart => variation_depth += 1,
+        Token::VariationEnd => variation_depth -= 1,
+        _ => {}
+      }
+      continue;
+    }
+
+    match token {
+      Token::Tag(name, value) => {
+        if !builder.game.moves.is_empty() { // a tag after moves means the last game didnt have a result
+          games.push(std::mem::replace(&mut builder, GameBuilder::new()).finish()?);
+        }
+        builder.game.set_header(&name, &value);
+      },
+      Token::Comment(comment) => {
+        let target = match builder.game.moves.last_mut() {
+          Some(pgn_move) => &mut pgn_move.comment,
+          None => &mut builder.game.comment,
+        };
+        match target {
+          Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&comment);
+          },
+          None => *target = Some(comment),
+        }
+      },
+      Token::Nag(nag) => {
+        if let Some(pgn_move) = builder.game.moves.last_mut() {
+          pgn_move.nags.push(nag);
+        }
+      },
+      Token::Move(san) => {
+        let ply = builder.game.moves.len() + 1;
+        let board = builder.board()?;
+        let piece_move = board.parse_san(&san).map_err(|reason| PgnError::InvalidMove { ply, san: san.clone(), reason })?;
+        let written_san = board.move_to_san(piece_move);
+        board.make_move(piece_move);
+
+        builder.game.moves.push(PgnMove {
+          piece_move,
+          san: written_san,
+          nags: Vec::new(),
+          comment: None,
+        });
+      },
+      Token::Result(result) => {
+        builder.game.result = result;
+        games.push(std::mem::replace(&mut builder, GameBuilder::new()).finish()?);
+      },
+      Token::VariationStart => variation_depth += 1,
+      Token::VariationEnd => {}, // a stray one, nothing to close
+    }
+  }
+  if variation_depth > 0 {
+    return Err(PgnError::UnterminatedVariation);
+  }
+  if builder.has_content() {
+    games.push(builder.finish()?);
+  }
+
+  Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board_representation::MoveKind;
+
+  const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move already.} 4. dxe5 Bxf3 5. Qxf3 dxe5
+6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5?! (9... Qb4+ 10. Qxb4 Bxb4) 10. Nxb5! cxb5
+11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8
+17. Rd8# 1-0
+"#;
+
+  #[test]
+  fn reads_annotated_game() {
+    let games = parse_pgn(OPERA_GAME).unwrap();
+    assert_eq!(games.len(), 1);
+
+    let game = &games[0];
+    assert_eq!(game.get_header("White"), Some("Paul Morphy"));
+    assert_eq!(game.moves.len(), 33);
+    assert_eq!(game.result, GameResult::WhiteWins);
+    assert_eq!(game.moves[5].comment.as_deref(), Some("This is a weak move already."));
+    assert_eq!(game.moves[17].nags, vec![6]);
+    assert_eq!(game.moves[18].nags, vec![1]);
+    assert_eq!(game.get_board().status(), GameStatus::Checkmate(Color::White));
+  }
+
+  #[test]
+  fn written_games_read_back_the_same() {
+    let game = &parse_pgn(OPERA_GAME).unwrap()[0];
+    let reread = &parse_pgn(&game.to_pgn()).unwrap()[0];
+    assert_eq!(reread.to_pgn(), game.to_pgn());
+    assert_eq!(reread.headers, game.headers);
+
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 20";
+    let mut board = Board::new(fen);
+    let moves = vec![board.parse_san("O-O").unwrap()];
+    board.make_move(moves[0]);
+    let game = PgnGame::new(&Board::new(fen), &moves, GameResult::Unknown);
+    let pgn = game.to_pgn();
+    assert!(pgn.contains("[FEN \"r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 20\"]"));
+    assert!(pgn.contains("20... O-O *"));
+    assert_eq!(parse_pgn(&pgn).unwrap()[0].get_board().to_fen(), board.to_fen());
+  }
+
+  #[test]
+  fn reports_illegal_moves() {
+    match parse_pgn("1. e4 e5 2. Ke3 *") {
+      Err(PgnError::InvalidMove { ply, san, .. }) => assert_eq!((ply, san.as_str()), (3, "Ke3")),
+      _ => panic!("Ke3 should not be legal"),
+    }
+  }
+
+  #[test]
+  fn reads_en_passant_marks_and_brackets_in_tags() {
+    let game = &parse_pgn("[Event \"a]b\"]\n[Site \"\\\"quoted]\\\"\"]\n\n1. e4 Nf6 2. e5 d5 3. exd6 e.p. *").unwrap()[0];
+    assert_eq!(game.get_header("Event"), Some("a]b"));
+    assert_eq!(game.get_header("Site"), Some("\"quoted]\""));
+    assert_eq!(game.moves.len(), 5);
+    assert_eq!(game.moves[4].piece_move.get_kind(), MoveKind::EnPassant);
+  }
+
+  #[test]
+  fn reports_stray_closing_brackets() {
+    for (pgn, c) in [("1. e4 } e5 *", '}'), ("1. e4 e5 ] *", ']')] {
+      match parse_pgn(pgn) {
+        Err(PgnError::UnexpectedCharacter(found)) => assert_eq!(found, c),
+        _ => panic!("{} should not parse", pgn),
+      }
+    }
+  }
+
+  #[test]
+  fn chess960_games() {
+    let start = Board::new_chess960(518); // the normal start position, so only the variant tag says it's chess960
+    let mut board = start.clone();
+    let mut moves = Vec::new();
+    for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O"] {
+      let piece_move = board.parse_san(san).unwrap();
+      board.make_move(piece_move);
+      moves.push(piece_move);
+    }
+    assert_eq!(moves[6].to_uci(), "e1h1");
+
+    let pgn = PgnGame::new(&start, &moves, GameResult::Unknown).to_pgn();
+    assert!(pgn.contains("[Variant \"Chess960\"]"));
+    let game = &parse_pgn(&pgn).unwrap()[0];
+    assert!(game.is_chess960);
+    assert_eq!(game.get_board().to_fen(), board.to_fen());
+  }
+}
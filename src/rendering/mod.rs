@@ -1,3 +1,9 @@
 // includes piece_sprite and square. allows for main.rs to access these modules
 pub mod piece_sprite;
-pub mod square;
\ No newline at end of file
+pub mod square;
+
+use macroquad::prelude::*;
+
+pub fn contains(rect: Rect, point: Vec2) -> bool {
+  (point.x >= rect.x && point.x <= rect.x + rect.w) && (point.y >= rect.y && point.y <= rect.y + rect.h)
+}
@@ -1,6 +1,6 @@
 use macroquad::prelude::*;
-use crate::utils::contains;
-use crate::utils::PieceType;
+use crate::rendering::contains;
+use chess::utils::Piece;
 
 pub const TEXTURE_PATH: &str = "assets/pieces.png";
 const TEXTURE_SIZE: i32 = 133;
@@ -8,7 +8,7 @@ const TEXTURE_SIZE: i32 = 133;
 fn draw_from_atlas(atlas: &Texture2D, sprite_rect: Rect, texture_mask: Rect) {
   let params = DrawTextureParams {
     dest_size: Some(vec2(sprite_rect.w, sprite_rect.h)), // sets onscreen size
-    source: Some(Rect {x: texture_mask.x as f32, y: texture_mask.y as f32, w: texture_mask.w, h: texture_mask.h}), // gets the sprite from the atlas
+    source: Some(Rect {x: texture_mask.x, y: texture_mask.y, w: texture_mask.w, h: texture_mask.h}), // gets the sprite from the atlas
     ..Default::default() // sets the rest of the parameters to their default
   };
 
@@ -19,7 +19,7 @@ fn draw_from_atlas(atlas: &Texture2D, sprite_rect: Rect, texture_mask: Rect) {
 #[derive(Clone)]
 pub struct PieceSprite {
   texture: Texture2D,
-  piece_type: PieceType,
+  piece: Piece,
   square: i32,
   mouse_on_sprite: bool,
   
@@ -27,33 +27,19 @@ pub struct PieceSprite {
   pub moved_piece: bool,
 }
 impl PieceSprite {
-  pub fn new(sprite_size: f32, sprite_texture: &Texture2D, sprite_type: PieceType, sprite_square: i32) -> Self {
+  pub fn new(sprite_size: f32, sprite_texture: &Texture2D, sprite_piece: Piece, sprite_square: i32) -> Self {
     Self {
       texture: sprite_texture.clone(),
-      piece_type: sprite_type,
+      piece: sprite_piece,
       square: sprite_square,
       mouse_on_sprite: false,
       
-      rect: Rect::new(0.0, 0.0, sprite_size as f32, sprite_size as f32),
+      rect: Rect::new(0.0, 0.0, sprite_size, sprite_size),
       moved_piece: false,
     }
   }
-  fn get_sprite_coords(key: &PieceType) -> (i32, i32) { // retufns coordinates of sprite on the atlas
-    let sprite_map = [
-      (0, 0),
-      (1, 0),
-      (2, 0),
-      (3, 0),
-      (4, 0),
-      (5, 0),
-      (0, 1),
-      (1, 1),
-      (2, 1),
-      (3, 1),
-      (4, 1),
-      (5, 1),
-    ];
-    return sprite_map[*key as usize];
+  fn get_sprite_coords(piece: Piece) -> (i32, i32) { // retufns coordinates of sprite on the atlas, white's pieces are on the top row
+    (piece.kind as i32, piece.color as i32)
   }
 
   pub fn handle_mousedown(&mut self) {
@@ -66,13 +52,13 @@ impl PieceSprite {
   }
   
   pub fn draw(&self) {
-    let (x, y) = Self::get_sprite_coords(&self.piece_type);
+    let (x, y) = Self::get_sprite_coords(self.piece);
     let texture_mask = Rect::new((x * TEXTURE_SIZE) as f32, (y * TEXTURE_SIZE) as f32 , TEXTURE_SIZE as f32, TEXTURE_SIZE as f32);
     draw_from_atlas(&self.texture, self.rect, texture_mask);
   }
 
-  pub fn get_piecetype(&self) -> PieceType {
-    self.piece_type
+  pub fn get_piece(&self) -> Piece {
+    self.piece
   }
   pub fn get_square(&self) -> i32 {
     self.square
@@ -1,5 +1,5 @@
 use macroquad::prelude::*;
-use crate::utils::contains;
+use crate::rendering::contains;
 
 pub const LIGHTSQUARE: Color = Color::new(0.95, 0.86, 0.71, 1.00);
 pub const DARKSQUARE: Color = Color::new(0.71, 0.55, 0.4, 1.00);
@@ -22,7 +22,7 @@ impl Square {
 
   pub fn handle_mouseover(&self) -> bool {
     let mouse_pos = mouse_position().into();
-    return contains(self.rect, mouse_pos);
+    contains(self.rect, mouse_pos)
   }
   pub fn draw(&self) {
     draw_rectangle(self.rect.x, self.rect.y, self.rect.w, self.rect.h, self.colour);
@@ -1,67 +1,88 @@
-use macroquad::prelude::*;
+use std::fmt;
+use std::ops::Not;
 
-#[derive(Copy, Clone)]
-pub enum PieceType {
-  WhiteKing,
-  WhiteQueen,
-  WhiteBishop,
-  WhiteKnight,
-  WhiteRook,
-  WhitePawn,
-  BlackKing,
-  BlackQueen,
-  BlackBishop,
-  BlackKnight,
-  BlackRook,
-  BlackPawn
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Color {
+  White,
+  Black
 }
-impl PieceType {
-  pub fn iter() -> impl Iterator<Item = Self> {
-    const VARIANTS: &[PieceType; 12] = &[
-      PieceType::WhiteKing,
-      PieceType::WhiteQueen,
-      PieceType::WhiteBishop,
-      PieceType::WhiteKnight,
-      PieceType::WhiteRook,
-      PieceType::WhitePawn,
-      PieceType::BlackKing,
-      PieceType::BlackQueen,
-      PieceType::BlackBishop,
-      PieceType::BlackKnight,
-      PieceType::BlackRook,
-      PieceType::BlackPawn
-    ];
-    VARIANTS.iter().copied()
+impl Color {
+  pub fn from_is_white(is_white: bool) -> Self {
+    if is_white { Color::White } else { Color::Black }
   }
+  pub fn is_white(self) -> bool {
+    self == Color::White
+  }
+}
+impl fmt::Display for Color {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", if self.is_white() { "white" } else { "black" })
+  }
+}
+impl Not for Color {
+  type Output = Self;
 
-  pub fn get_colour_types(is_white: bool) -> [Self; 6] {
-    return if is_white {
-      [
-        PieceType::WhiteKing,
-        PieceType::WhiteQueen,
-        PieceType::WhiteBishop,
-        PieceType::WhiteKnight,
-        PieceType::WhiteRook,
-        PieceType::WhitePawn
-      ]
-    } else {
-      [
-        PieceType::BlackKing,
-        PieceType::BlackQueen,
-        PieceType::BlackBishop,
-        PieceType::BlackKnight,
-        PieceType::BlackRook,
-        PieceType::BlackPawn
-      ]
+  fn not(self) -> Self {
+    match self {
+      Color::White => Color::Black,
+      Color::Black => Color::White,
     }
   }
 }
-impl PartialEq for PieceType {
-  fn eq(&self, other: &Self) -> bool {
-  *self as usize == *other as usize
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PieceKind {
+  King,
+  Queen,
+  Bishop,
+  Knight,
+  Rook,
+  Pawn
 }
+impl PieceKind {
+  pub const ALL: [PieceKind; 6] = [
+    PieceKind::King,
+    PieceKind::Queen,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Rook,
+    PieceKind::Pawn
+  ];
+
+  pub fn to_char(self) -> char { // lowercase, the way a black piece is written in a FEN
+    match self {
+      PieceKind::King => 'k',
+      PieceKind::Queen => 'q',
+      PieceKind::Bishop => 'b',
+      PieceKind::Knight => 'n',
+      PieceKind::Rook => 'r',
+      PieceKind::Pawn => 'p',
+    }
+  }
+  pub fn from_char(c: char) -> Option<Self> { // either case
+    PieceKind::ALL.into_iter().find(|kind| kind.to_char() == c.to_ascii_lowercase())
+  }
 }
 
-pub fn contains(rect: Rect, point: Vec2) -> bool {
-  (point.x >= rect.x && point.x <= rect.x + rect.w) && (point.y >= rect.y && point.y <= rect.y + rect.h)
-}
\ No newline at end of file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Piece {
+  pub color: Color,
+  pub kind: PieceKind,
+}
+impl Piece {
+  pub fn new(color: Color, kind: PieceKind) -> Self {
+    Self { color, kind }
+  }
+  pub fn index(self) -> usize { // where the piece's bitboard is, white's six pieces come first in the order of PieceKind
+    self.color as usize * PieceKind::ALL.len() + self.kind as usize
+  }
+  pub fn iter() -> impl Iterator<Item = Self> { // every piece, in index order
+    [Color::White, Color::Black].into_iter().flat_map(|color| PieceKind::ALL.into_iter().map(move |kind| Piece::new(color, kind)))
+  }
+  pub fn is_white(self) -> bool {
+    self.color.is_white()
+  }
+}